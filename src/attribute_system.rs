@@ -0,0 +1,82 @@
+use super::{AttributeBonus, Attributes, Equipped};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Recalculates everyone's attribute modifiers from what they have equipped,
+/// so putting on or taking off an item changes the bonuses at once
+pub struct AttributeSystem {}
+
+impl<'a> System<'a> for AttributeSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Attributes>,
+        ReadStorage<'a, AttributeBonus>,
+        ReadStorage<'a, Equipped>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut attributes, attribute_bonuses, equipped) = data;
+
+        let mut totals: HashMap<Entity, AttributeBonus> = HashMap::new();
+        for (bonus, equipped_by) in (&attribute_bonuses, &equipped).join() {
+            let total = totals.entry(equipped_by.owner).or_default();
+            total.might += bonus.might;
+            total.fitness += bonus.fitness;
+            total.quickness += bonus.quickness;
+            total.intelligence += bonus.intelligence;
+        }
+
+        for (entity, attributes) in (&entities, &mut attributes).join() {
+            let total = totals.remove(&entity).unwrap_or_default();
+            attributes.might.set_modifiers(total.might);
+            attributes.fitness.set_modifiers(total.fitness);
+            attributes.quickness.set_modifiers(total.quickness);
+            attributes.intelligence.set_modifiers(total.intelligence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EquipmentSlot;
+
+    #[test]
+    fn bonuses_follow_what_is_equipped() {
+        let mut ecs = World::new();
+        ecs.register::<Attributes>();
+        ecs.register::<AttributeBonus>();
+        ecs.register::<Equipped>();
+
+        let wearer = ecs
+            .create_entity()
+            .with(Attributes::new(10, 10, 14, 10))
+            .build();
+        let shield = ecs
+            .create_entity()
+            .with(AttributeBonus {
+                quickness: -2,
+                ..AttributeBonus::default()
+            })
+            .with(Equipped {
+                owner: wearer,
+                slot: EquipmentSlot::Shield,
+            })
+            .build();
+
+        AttributeSystem {}.run_now(&ecs);
+        {
+            let attributes = ecs.read_storage::<Attributes>();
+            let quickness = attributes.get(wearer).unwrap().quickness;
+            assert_eq!(quickness.value(), 12);
+            assert_eq!(quickness.bonus, 1);
+        }
+
+        ecs.write_storage::<Equipped>().remove(shield);
+        AttributeSystem {}.run_now(&ecs);
+        let attributes = ecs.read_storage::<Attributes>();
+        let quickness = attributes.get(wearer).unwrap().quickness;
+        assert_eq!(quickness.value(), 14);
+        assert_eq!(quickness.bonus, 2);
+    }
+}
@@ -0,0 +1,273 @@
+#![allow(clippy::needless_return)]
+//! Generates a single level without opening a window and prints it, so a
+//! generator's output can be pasted into a code review.
+//!
+//! cargo run --bin mapgen -- --seed 42 --depth 3 --builder bsp --format json
+use hellorust::map_builders::{named_builder, random_builder, BUILDER_NAMES};
+use hellorust::{Map, Position, RunSeed, TileType};
+use serde_json::json;
+use std::process;
+
+const USAGE: &str = "usage: mapgen [--seed N] [--depth N] [--builder NAME] [--format ascii|json]";
+
+#[derive(PartialEq, Debug)]
+enum Format {
+    Ascii,
+    Json,
+}
+
+#[derive(PartialEq, Debug)]
+struct Options {
+    seed: u64,
+    depth: i32,
+    /// None picks a builder the same way the game does
+    builder: Option<String>,
+    format: Format,
+}
+
+/// Everything a builder produced, ready to be printed
+struct Level {
+    map: Map,
+    start: Position,
+    spawns: Vec<(usize, String)>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let level = generate(&options);
+    match options.format {
+        Format::Ascii => print!("{}", to_ascii(&options, &level)),
+        Format::Json => println!("{}", to_json(&options, &level)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        seed: RunSeed::random().value,
+        depth: 1,
+        builder: None,
+        format: Format::Ascii,
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        if flag == "--help" || flag == "-h" {
+            return Err(format!("builders: {}", BUILDER_NAMES.join(", ")));
+        }
+        let value = args
+            .get(i + 1)
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag {
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed: {}", value))?
+            }
+            "--depth" => {
+                options.depth = value
+                    .parse()
+                    .map_err(|_| format!("invalid depth: {}", value))?
+            }
+            "--builder" => {
+                if !BUILDER_NAMES.contains(&value.as_str()) {
+                    return Err(format!(
+                        "unknown builder: {} (expected one of {})",
+                        value,
+                        BUILDER_NAMES.join(", ")
+                    ));
+                }
+                options.builder = Some(value.clone());
+            }
+            "--format" => {
+                options.format = match value.as_str() {
+                    "ascii" => Format::Ascii,
+                    "json" => Format::Json,
+                    _ => return Err(format!("unknown format: {}", value)),
+                }
+            }
+            _ => return Err(format!("unknown option: {}", flag)),
+        }
+        i += 2;
+    }
+
+    return Ok(options);
+}
+
+/// Builds the level exactly as the game would for this seed and depth
+fn generate(options: &Options) -> Level {
+    let mut rng = RunSeed {
+        value: options.seed,
+    }
+    .level_rng(options.depth);
+    let mut builder = match &options.builder {
+        Some(name) => named_builder(name, options.depth, &mut rng).unwrap(),
+        None => random_builder(options.depth, &mut rng),
+    };
+    builder.build_map(&mut rng);
+
+    return Level {
+        map: builder.get_map(),
+        start: builder.get_starting_position(),
+        spawns: builder.get_spawn_list().clone(),
+    };
+}
+
+fn tile_glyph(tile: TileType) -> char {
+    return match tile {
+        TileType::Wall => '#',
+        TileType::Floor => '.',
+        TileType::StairsDown => '>',
+        TileType::StairsUp => '<',
+        TileType::DoorClosed => '+',
+        TileType::DoorOpen => '\'',
+        TileType::SecretDoor => 'S',
+        TileType::Road => ',',
+        TileType::ShallowWater => '~',
+        TileType::DeepWater => 'W',
+        TileType::Lava => 'L',
+        TileType::Bridge => '=',
+    };
+}
+
+/// The map with the start marked `@` and spawn points `*`, followed by the
+/// lists of rooms, stairs and spawns
+fn to_ascii(options: &Options, level: &Level) -> String {
+    let map = &level.map;
+    let mut glyphs: Vec<char> = map.tiles.iter().map(|tile| tile_glyph(*tile)).collect();
+    for (idx, _name) in level.spawns.iter() {
+        glyphs[*idx] = '*';
+    }
+    glyphs[map.xy_idx(level.start.x, level.start.y)] = '@';
+
+    let mut out = format!(
+        "seed {} depth {} builder {} ({}x{})\n",
+        options.seed,
+        options.depth,
+        options.builder.as_deref().unwrap_or("random"),
+        map.width,
+        map.height
+    );
+    for row in glyphs.chunks(map.width as usize) {
+        out.extend(row.iter());
+        out.push('\n');
+    }
+
+    out += &format!("start: {},{}\n", level.start.x, level.start.y);
+    for (x, y) in find_tiles(map, TileType::StairsDown) {
+        out += &format!("stairs down: {},{}\n", x, y);
+    }
+    for (x, y) in find_tiles(map, TileType::StairsUp) {
+        out += &format!("stairs up: {},{}\n", x, y);
+    }
+    for room in map.rooms.iter() {
+        out += &format!("room: {},{} to {},{}\n", room.x1, room.y1, room.x2, room.y2);
+    }
+    for (idx, name) in level.spawns.iter() {
+        let x = *idx as i32 % map.width;
+        let y = *idx as i32 / map.width;
+        out += &format!("spawn: {},{} {}\n", x, y, name);
+    }
+    return out;
+}
+
+fn to_json(options: &Options, level: &Level) -> serde_json::Value {
+    let map = &level.map;
+    let rows: Vec<String> = map
+        .tiles
+        .chunks(map.width as usize)
+        .map(|row| row.iter().map(|tile| tile_glyph(*tile)).collect())
+        .collect();
+    let spawns: Vec<serde_json::Value> = level
+        .spawns
+        .iter()
+        .map(|(idx, name)| {
+            json!({
+                "x": *idx as i32 % map.width,
+                "y": *idx as i32 / map.width,
+                "name": name,
+            })
+        })
+        .collect();
+
+    return json!({
+        "seed": options.seed,
+        "depth": options.depth,
+        "builder": options.builder.as_deref().unwrap_or("random"),
+        "width": map.width,
+        "height": map.height,
+        "tiles": rows,
+        "start": { "x": level.start.x, "y": level.start.y },
+        "stairs_down": points(find_tiles(map, TileType::StairsDown)),
+        "stairs_up": points(find_tiles(map, TileType::StairsUp)),
+        "rooms": map.rooms,
+        "spawns": spawns,
+    });
+}
+
+fn find_tiles(map: &Map, tile: TileType) -> Vec<(i32, i32)> {
+    return map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_idx, t)| **t == tile)
+        .map(|(idx, _t)| (idx as i32 % map.width, idx as i32 / map.width))
+        .collect();
+}
+
+fn points(tiles: Vec<(i32, i32)>) -> Vec<serde_json::Value> {
+    return tiles
+        .iter()
+        .map(|(x, y)| json!({ "x": x, "y": y }))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        return line.split_whitespace().map(|arg| arg.to_string()).collect();
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = parse_args(&args("--seed 42 --depth 3 --builder bsp --format json")).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                seed: 42,
+                depth: 3,
+                builder: Some("bsp".to_string()),
+                format: Format::Json,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_builders_and_missing_values() {
+        assert!(parse_args(&args("--builder maze")).is_err());
+        assert!(parse_args(&args("--seed")).is_err());
+        assert!(parse_args(&args("--depth deep")).is_err());
+    }
+
+    #[test]
+    fn ascii_output_has_one_line_per_map_row() {
+        let options = parse_args(&args("--seed 7 --depth 2 --builder simple")).unwrap();
+        let level = generate(&options);
+        let rows = to_ascii(&options, &level)
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.contains(':'))
+            .count();
+        assert_eq!(rows as i32, level.map.height);
+    }
+}
@@ -0,0 +1,69 @@
+use super::{draw_map, Hidden, Map, Position, Renderable};
+use rltk::{Point, Rltk};
+use specs::prelude::*;
+
+/// The part of the console the map is drawn in; the rows below it hold the HUD
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// World position of the top-left corner of the screen. The camera is kept
+/// centred on the player.
+pub fn view_origin(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    return Point::new(
+        player_pos.x - VIEW_WIDTH / 2,
+        player_pos.y - VIEW_HEIGHT / 2,
+    );
+}
+
+/// Converts a console position (such as the mouse) into a map position
+pub fn screen_to_world(ecs: &World, screen_x: i32, screen_y: i32) -> Point {
+    let origin = view_origin(ecs);
+    return Point::new(origin.x + screen_x, origin.y + screen_y);
+}
+
+/// Converts a map position into a console position, if it is on screen
+pub fn world_to_screen(ecs: &World, world: Point) -> Option<Point> {
+    let origin = view_origin(ecs);
+    let screen = Point::new(world.x - origin.x, world.y - origin.y);
+    if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT {
+        return None;
+    }
+    return Some(screen);
+}
+
+/// Draws the map and every visible entity around the player
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let origin = view_origin(ecs);
+    draw_map(&map, origin, VIEW_WIDTH, VIEW_HEIGHT, ctx);
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let mut data = (&positions, &renderables, !&hidden)
+        .join()
+        .collect::<Vec<_>>();
+    data.sort_by_key(|&a| std::cmp::Reverse(a.1.render_order));
+
+    for (pos, render, _hidden) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        // whoever stands in the dark can't be made out, even in plain sight
+        if map.visible_tiles[idx] && map.is_lit(idx) {
+            if let Some(screen) = world_to_screen(ecs, Point::new(pos.x, pos.y)) {
+                ctx.set(screen.x, screen.y, render.fg, render.bg, render.glyph)
+            }
+        }
+    }
+}
+
+/// Draws a map during generation, centred on the middle of the map since
+/// there is no player to follow yet
+pub fn render_debug_map(map: &Map, ctx: &mut Rltk) {
+    let origin = Point::new(
+        map.width / 2 - VIEW_WIDTH / 2,
+        map.height / 2 - VIEW_HEIGHT / 2,
+    );
+    draw_map(map, origin, VIEW_WIDTH, VIEW_HEIGHT, ctx);
+}
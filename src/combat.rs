@@ -0,0 +1,366 @@
+//! Resolves a single attack: a d20 to hit against armor class, then the
+//! weapon's damage dice, then the target's resistances to that kind of
+//! damage. Kept free of the ECS so it can be tested alone.
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A number of dice of one size plus a flat bonus, such as "1d8+2"
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Dice {
+    pub n_dice: i32,
+    pub die_type: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn new(n_dice: i32, die_type: i32, bonus: i32) -> Dice {
+        return Dice {
+            n_dice,
+            die_type,
+            bonus,
+        };
+    }
+
+    /// Reads dice written as "1d4", "2d6+1" or "1d8-1"
+    pub fn parse(text: &str) -> Result<Dice, String> {
+        let invalid = || format!("'{}' is not a dice roll such as 1d8+2", text);
+
+        let (n_dice, rest) = text.split_once('d').ok_or_else(invalid)?;
+        let (die_type, bonus) = match rest.find(['+', '-']) {
+            Some(sign) => (&rest[..sign], &rest[sign..]),
+            None => (rest, "+0"),
+        };
+
+        let n_dice: i32 = n_dice.parse().map_err(|_| invalid())?;
+        let die_type: i32 = die_type.parse().map_err(|_| invalid())?;
+        let bonus: i32 = bonus
+            .trim_start_matches('+')
+            .parse()
+            .map_err(|_| invalid())?;
+        if n_dice < 1 || die_type < 1 {
+            return Err(invalid());
+        }
+        return Ok(Dice::new(n_dice, die_type, bonus));
+    }
+
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        return rng.roll_dice(self.n_dice, self.die_type) + self.bonus;
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}d{}", self.n_dice, self.die_type)?;
+        if self.bonus != 0 {
+            write!(f, "{:+}", self.bonus)?;
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DamageType {
+    #[default]
+    Bludgeoning,
+    Slashing,
+    Piercing,
+    Fire,
+    Cold,
+    Poison,
+    Force,
+}
+
+impl DamageType {
+    /// What this kind of damage does to its victim, for the log
+    pub fn verb(self) -> &'static str {
+        return match self {
+            DamageType::Bludgeoning => "bashes",
+            DamageType::Slashing => "slashes",
+            DamageType::Piercing => "pierces",
+            DamageType::Fire => "scorches",
+            DamageType::Cold => "freezes",
+            DamageType::Poison => "poisons",
+            DamageType::Force => "blasts",
+        };
+    }
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Slashing => "slashing",
+            DamageType::Piercing => "piercing",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+            DamageType::Force => "force",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+/// How badly one kind of damage hurts someone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Susceptibility {
+    Normal,
+    /// Takes half damage
+    Resistant,
+    /// Takes double damage
+    Weak,
+    /// Takes no damage at all
+    Immune,
+}
+
+impl Susceptibility {
+    /// Sums up what a creature and everything it wears say about one kind of
+    /// damage. Any immunity wins; otherwise resistances and weaknesses cancel
+    /// each other out.
+    pub fn combine(all: &[Susceptibility]) -> Susceptibility {
+        if all.contains(&Susceptibility::Immune) {
+            return Susceptibility::Immune;
+        }
+        let resistances = all
+            .iter()
+            .filter(|s| **s == Susceptibility::Resistant)
+            .count();
+        let weaknesses = all.iter().filter(|s| **s == Susceptibility::Weak).count();
+        if resistances > weaknesses {
+            return Susceptibility::Resistant;
+        }
+        if weaknesses > resistances {
+            return Susceptibility::Weak;
+        }
+        return Susceptibility::Normal;
+    }
+
+    pub fn apply(self, amount: i32) -> i32 {
+        return match self {
+            Susceptibility::Normal => amount,
+            Susceptibility::Resistant => amount / 2,
+            Susceptibility::Weak => amount * 2,
+            Susceptibility::Immune => 0,
+        };
+    }
+
+    /// Added to the end of a damage message, e.g. " (resisted)"
+    pub fn label(self) -> &'static str {
+        return match self {
+            Susceptibility::Normal => "",
+            Susceptibility::Resistant => " (resisted)",
+            Susceptibility::Weak => " (weak)",
+            Susceptibility::Immune => " (immune)",
+        };
+    }
+}
+
+/// Everything the attacker brings to a single swing
+#[derive(Debug, Clone, Copy)]
+pub struct Attack {
+    /// Added to the d20 roll
+    pub hit_bonus: i32,
+    pub damage: Dice,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HitKind {
+    /// A natural 1, which always misses
+    Fumble,
+    Miss,
+    Hit,
+    /// A natural 20, which always hits and rolls the damage dice twice
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AttackResult {
+    pub kind: HitKind,
+    pub natural_roll: i32,
+    pub hit_bonus: i32,
+    pub armor_class: i32,
+    /// The dice that were rolled for damage, doubled on a critical
+    pub damage_dice: Dice,
+    /// Zero unless the attack hit
+    pub damage: i32,
+}
+
+impl AttackResult {
+    pub fn is_hit(&self) -> bool {
+        return matches!(self.kind, HitKind::Hit | HitKind::Critical);
+    }
+
+    /// Explains the rolls, e.g. "d20: 14+2 = 16 vs AC 12, 1d8+2: 7"
+    pub fn breakdown(&self) -> String {
+        let to_hit = format!(
+            "d20: {}{:+} = {} vs AC {}",
+            self.natural_roll,
+            self.hit_bonus,
+            self.natural_roll + self.hit_bonus,
+            self.armor_class
+        );
+        if !self.is_hit() {
+            return to_hit;
+        }
+        return format!("{}, {}: {}", to_hit, self.damage_dice, self.damage);
+    }
+}
+
+/// Rolls one attack against a target with the given armor class
+pub fn resolve_attack(
+    attack: &Attack,
+    armor_class: i32,
+    rng: &mut RandomNumberGenerator,
+) -> AttackResult {
+    let natural_roll = rng.roll_dice(1, 20);
+    return resolve_attack_roll(attack, armor_class, natural_roll, rng);
+}
+
+/// Like `resolve_attack`, for a d20 that has already been rolled
+pub fn resolve_attack_roll(
+    attack: &Attack,
+    armor_class: i32,
+    natural_roll: i32,
+    rng: &mut RandomNumberGenerator,
+) -> AttackResult {
+    let kind = if natural_roll == 1 {
+        HitKind::Fumble
+    } else if natural_roll == 20 {
+        HitKind::Critical
+    } else if natural_roll + attack.hit_bonus >= armor_class {
+        HitKind::Hit
+    } else {
+        HitKind::Miss
+    };
+
+    let mut damage_dice = attack.damage;
+    if kind == HitKind::Critical {
+        damage_dice.n_dice *= 2;
+    }
+    let damage = match kind {
+        // a hit always does something, however weak the attacker
+        HitKind::Hit | HitKind::Critical => i32::max(1, damage_dice.roll(rng)),
+        HitKind::Miss | HitKind::Fumble => 0,
+    };
+
+    return AttackResult {
+        kind,
+        natural_roll,
+        hit_bonus: attack.hit_bonus,
+        armor_class,
+        damage_dice,
+        damage,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONGSWORD: Attack = Attack {
+        hit_bonus: 2,
+        damage: Dice {
+            n_dice: 1,
+            die_type: 8,
+            bonus: 0,
+        },
+    };
+
+    #[test]
+    fn dice_are_parsed_and_printed() {
+        assert_eq!(Dice::parse("1d4"), Ok(Dice::new(1, 4, 0)));
+        assert_eq!(Dice::parse("2d6+1"), Ok(Dice::new(2, 6, 1)));
+        assert_eq!(Dice::parse("1d8-1"), Ok(Dice::new(1, 8, -1)));
+        assert!(Dice::parse("d8").is_err());
+        assert!(Dice::parse("1d").is_err());
+        assert!(Dice::parse("0d6").is_err());
+        assert!(Dice::parse("sword").is_err());
+
+        assert_eq!(Dice::new(2, 6, 1).to_string(), "2d6+1");
+        assert_eq!(Dice::new(1, 8, -1).to_string(), "1d8-1");
+        assert_eq!(Dice::new(1, 4, 0).to_string(), "1d4");
+    }
+
+    #[test]
+    fn rolls_meet_armor_class_to_hit() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(
+            resolve_attack_roll(&LONGSWORD, 12, 10, &mut rng).kind,
+            HitKind::Hit
+        );
+        assert_eq!(
+            resolve_attack_roll(&LONGSWORD, 13, 10, &mut rng).kind,
+            HitKind::Miss
+        );
+    }
+
+    #[test]
+    fn natural_ones_and_twenties_ignore_armor_class() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let fumble = resolve_attack_roll(&LONGSWORD, 0, 1, &mut rng);
+        assert_eq!(fumble.kind, HitKind::Fumble);
+        assert_eq!(fumble.damage, 0);
+
+        let critical = resolve_attack_roll(&LONGSWORD, 50, 20, &mut rng);
+        assert_eq!(critical.kind, HitKind::Critical);
+        assert_eq!(critical.damage_dice, Dice::new(2, 8, 0));
+    }
+
+    #[test]
+    fn damage_stays_within_the_dice() {
+        let mut rng = RandomNumberGenerator::seeded(3);
+        for _ in 0..1000 {
+            let hit = resolve_attack_roll(&LONGSWORD, 0, 10, &mut rng);
+            assert!((1..=8).contains(&hit.damage));
+
+            let critical = resolve_attack_roll(&LONGSWORD, 0, 20, &mut rng);
+            assert!((2..=16).contains(&critical.damage));
+        }
+    }
+
+    #[test]
+    fn weak_hits_still_do_damage() {
+        let feeble = Attack {
+            hit_bonus: 0,
+            damage: Dice::new(1, 4, -5),
+        };
+        let mut rng = RandomNumberGenerator::seeded(5);
+        assert_eq!(resolve_attack_roll(&feeble, 0, 10, &mut rng).damage, 1);
+    }
+
+    #[test]
+    fn immunity_beats_everything_else() {
+        use Susceptibility::*;
+        assert_eq!(Susceptibility::combine(&[Weak, Immune, Resistant]), Immune);
+        assert_eq!(Immune.apply(12), 0);
+    }
+
+    #[test]
+    fn resistances_and_weaknesses_cancel_out() {
+        use Susceptibility::*;
+        assert_eq!(Susceptibility::combine(&[]), Normal);
+        assert_eq!(Susceptibility::combine(&[Resistant, Weak]), Normal);
+        assert_eq!(
+            Susceptibility::combine(&[Resistant, Resistant, Weak]),
+            Resistant
+        );
+        assert_eq!(Susceptibility::combine(&[Weak]), Weak);
+
+        assert_eq!(Resistant.apply(25), 12);
+        assert_eq!(Weak.apply(6), 12);
+        assert_eq!(Normal.apply(6), 6);
+    }
+
+    #[test]
+    fn the_breakdown_explains_each_roll() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let miss = resolve_attack_roll(&LONGSWORD, 15, 7, &mut rng);
+        assert_eq!(miss.breakdown(), "d20: 7+2 = 9 vs AC 15");
+
+        let hit = resolve_attack_roll(&LONGSWORD, 15, 18, &mut rng);
+        assert_eq!(
+            hit.breakdown(),
+            format!("d20: 18+2 = 20 vs AC 15, 1d8: {}", hit.damage)
+        );
+    }
+}
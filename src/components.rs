@@ -1,3 +1,5 @@
+use crate::combat::{DamageType, Dice, Susceptibility};
+use crate::gamesystem::{armor_class, attr_bonus};
 use rltk::RGB;
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
@@ -11,6 +13,14 @@ pub struct Position {
     pub y: i32,
 }
 
+/// Where an entity is on a level the player isn't on right now
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct OtherLevelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
@@ -32,6 +42,17 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// Which side an entity is on; the raws' faction table says how every
+/// faction reacts to every other
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+/// Stepped into shallow water; the next move is spent wading out of it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Wading {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 /// Name component
 pub struct Name {
@@ -42,12 +63,84 @@ pub struct Name {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
+/// One attribute: its natural value, what equipment and effects add to it,
+/// and the bonus that gives
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Attribute {
+    pub base: i32,
+    pub modifiers: i32,
+    pub bonus: i32,
+}
+
+impl Attribute {
+    pub fn new(base: i32) -> Attribute {
+        return Attribute {
+            base,
+            modifiers: 0,
+            bonus: attr_bonus(base),
+        };
+    }
+
+    pub fn value(&self) -> i32 {
+        return self.base + self.modifiers;
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: i32) {
+        self.modifiers = modifiers;
+        self.bonus = attr_bonus(self.value());
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct CombatStats {
-    pub max_hp: i32,
-    pub hp: i32,
-    pub defense: i32,
-    pub power: i32,
+pub struct Attributes {
+    /// Strength: how hard you hit
+    pub might: Attribute,
+    /// Toughness: how much punishment you can take
+    pub fitness: Attribute,
+    /// Agility: how hard you are to hit
+    pub quickness: Attribute,
+    pub intelligence: Attribute,
+}
+
+impl Attributes {
+    pub fn new(might: i32, fitness: i32, quickness: i32, intelligence: i32) -> Attributes {
+        return Attributes {
+            might: Attribute::new(might),
+            fitness: Attribute::new(fitness),
+            quickness: Attribute::new(quickness),
+            intelligence: Attribute::new(intelligence),
+        };
+    }
+
+    /// Armor class before anything worn is counted
+    pub fn armor_class(&self) -> i32 {
+        return armor_class(self.quickness.value());
+    }
+}
+
+/// Something that drains and refills, such as hit points
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Pool {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Pool {
+    /// A full pool
+    pub fn new(max: i32) -> Pool {
+        return Pool { current: max, max };
+    }
+
+    /// Adds up to `amount`, without going over the maximum
+    pub fn restore(&mut self, amount: i32) {
+        self.current = i32::min(self.max, self.current + amount);
+    }
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Pools {
+    pub hit_points: Pool,
+    pub level: i32,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -55,24 +148,70 @@ pub struct WantsToMelee {
     pub target: Entity,
 }
 
+/// One blow, before the victim's resistances are applied
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Damage {
+    pub amount: i32,
+    pub damage_type: DamageType,
+    /// Who or what did it, for the log
+    pub source: String,
+}
+
+impl Damage {
+    pub fn new(amount: i32, damage_type: DamageType, source: &str) -> Damage {
+        return Damage {
+            amount,
+            damage_type,
+            source: source.to_string(),
+        };
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<Damage>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, damage: Damage) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push(damage);
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![damage],
             };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
+/// Kinds of damage a creature, or whoever wears this, shrugs off, suffers
+/// extra from or ignores completely
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Resistances {
+    pub resistant: Vec<DamageType>,
+    pub weak: Vec<DamageType>,
+    pub immune: Vec<DamageType>,
+}
+
+impl Resistances {
+    /// What this says about one kind of damage, to be combined with
+    /// everything else the victim has going for (or against) it
+    pub fn susceptibilities(&self, damage_type: DamageType) -> Vec<Susceptibility> {
+        let mut found = Vec::new();
+        if self.resistant.contains(&damage_type) {
+            found.push(Susceptibility::Resistant);
+        }
+        if self.weak.contains(&damage_type) {
+            found.push(Susceptibility::Weak);
+        }
+        if self.immune.contains(&damage_type) {
+            found.push(Susceptibility::Immune);
+        }
+        return found;
+    }
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
@@ -114,6 +253,7 @@ pub struct Ranged {
 #[derive(Debug, Component, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub damage_type: DamageType,
 }
 
 #[derive(Debug, Component, ConvertSaveload, Clone)]
@@ -126,17 +266,54 @@ pub struct Confusion {
     pub turns: i32,
 }
 
+/// Not drawn until the player finds it
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+/// Fires when something steps onto the same tile
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+/// Moved this turn; entry triggers on the new tile get a chance to fire
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct EntityMoved {}
+
+/// Removed after it has fired once
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct SingleActivation {}
+
+/// Sends whatever triggers it to a random spot on the level
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
+pub struct TeleportsTarget {}
+
+/// Lights up every tile it can see within `range`, fading towards the edge
+#[derive(Debug, Component, ConvertSaveload, Clone)]
+pub struct LightSource {
+    pub range: i32,
+}
+
+/// Rolls on the named raws table when its owner dies, `chance` percent of
+/// the time, and drops whatever comes up where it fell
+#[derive(Debug, Component, ConvertSaveload, Clone)]
+pub struct LootTable {
+    pub table: String,
+    pub chance: i32,
+}
+
 pub struct SerializeMe;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub seed: u64,
+    pub dungeon_master: super::dungeon_master::DungeonMaster,
 }
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Melee,
     Shield,
+    Ranged,
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -150,16 +327,59 @@ pub struct Equipped {
     pub slot: EquipmentSlot,
 }
 
+/// A weapon's damage dice, and how much easier it makes it to hit
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct MeleeWeapon {
+    pub damage: Dice,
+    pub damage_type: DamageType,
+    pub hit_bonus: i32,
+}
+
+/// The claws, teeth or fists a creature fights with when it has no weapon
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct NaturalAttack {
+    pub damage: Dice,
+    pub damage_type: DamageType,
+}
+
+/// A bow or crossbow. Each shot uses up one of the named ammunition from
+/// the wielder's backpack.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub damage: Dice,
+    pub damage_type: DamageType,
+    pub hit_bonus: i32,
+    pub ammo: String,
+}
+
+/// A stack of arrows, bolts and the like. Picking up more with the same name
+/// adds to the stack.
 #[derive(Component, ConvertSaveload, Clone)]
-pub struct MeleePowerBonus {
-    pub power: i32,
+pub struct Ammunition {
+    pub count: i32,
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
+/// Adds to the wearer's armor class
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct DefenseBonus {
     pub defense: i32,
 }
 
+/// Raises or lowers the wearer's attributes while the item is equipped
+#[derive(Component, Serialize, Deserialize, Clone, Default)]
+pub struct AttributeBonus {
+    pub might: i32,
+    pub fitness: i32,
+    pub quickness: i32,
+    pub intelligence: i32,
+}
+
 #[derive(Debug, Component, ConvertSaveload, Clone)]
 pub struct WantsToRemoveEquipment {
     pub item: Entity,
@@ -1,37 +1,119 @@
-use super::{CombatStats, GameLog, Name, Player, RunState, SufferDamage};
+use super::{
+    combat::Susceptibility, raws::raws, Equipped, GameLog, LootTable, Map, Name, Player, Pools,
+    Position, Renderable, Resistances, RunState, SerializeMe, SufferDamage,
+};
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+/// How much of its colour a monster's corpse keeps
+const CORPSE_BRIGHTNESS: f32 = 0.5;
+
+/// What a dead entity leaves where it fell
+struct Remains {
+    pos: Position,
+    renderable: Option<Renderable>,
+    name: Option<String>,
+    loot: Option<String>,
+}
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
-        WriteStorage<'a, CombatStats>,
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, Pools>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Resistances>,
+        ReadStorage<'a, Equipped>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (
+            entities,
+            player_entity,
+            map,
+            mut log,
+            mut pools,
+            mut damage,
+            names,
+            positions,
+            resistances,
+            equipped,
+        ) = data;
+
+        for (victim, pools, damage) in (&entities, &mut pools, &damage).join() {
+            // the player hears about everything done to them, and sees what
+            // happens to others in plain view
+            let seen = victim == *player_entity
+                || positions
+                    .get(victim)
+                    .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+            for blow in damage.amount.iter() {
+                // resistances come from the victim and from what it wears
+                let mut found: Vec<Susceptibility> = Vec::new();
+                if let Some(own) = resistances.get(victim) {
+                    found.extend(own.susceptibilities(blow.damage_type));
+                }
+                for (worn, equipped_by) in (&resistances, &equipped).join() {
+                    if equipped_by.owner == victim {
+                        found.extend(worn.susceptibilities(blow.damage_type));
+                    }
+                }
+                let susceptibility = Susceptibility::combine(&found);
+                let amount = susceptibility.apply(blow.amount);
+                pools.hit_points.current -= amount;
+
+                if seen {
+                    let victim_name = names.get(victim).map_or("something", |n| &n.value);
+                    log.entries.push(capitalize(&format!(
+                        "{} {} {} for {} {} damage{}",
+                        the(&blow.source),
+                        blow.damage_type.verb(),
+                        the(victim_name),
+                        amount,
+                        blow.damage_type,
+                        susceptibility.label()
+                    )));
+                }
+            }
         }
 
         damage.clear();
     }
 }
 
+/// A name as it reads in the middle of a sentence, e.g. "the orc"
+fn the(name: &str) -> String {
+    return format!("the {}", name.to_lowercase());
+}
+
+fn capitalize(sentence: &str) -> String {
+    let mut chars = sentence.chars();
+    return match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+}
+
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
     // Using a scope to make the borrow checker happy
     {
-        let combat_stats = ecs.read_storage::<CombatStats>();
+        let pools = ecs.read_storage::<Pools>();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
 
-        for (victim, stats) in (&entities, &combat_stats).join() {
-            if stats.hp < 1 {
+        for (victim, pools) in (&entities, &pools).join() {
+            if pools.hit_points.current < 1 {
                 let player = players.get(victim);
 
                 match player {
@@ -50,7 +132,54 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    // Work out what each victim leaves behind before it is gone
+    let mut remains: Vec<Remains> = Vec::new();
+    {
+        let positions = ecs.read_storage::<Position>();
+        let renderables = ecs.read_storage::<Renderable>();
+        let names = ecs.read_storage::<Name>();
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let depth = ecs.fetch::<Map>().depth;
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+
+        for victim in dead.iter() {
+            if let Some(pos) = positions.get(*victim) {
+                let loot = loot_tables
+                    .get(*victim)
+                    .and_then(|loot| raws().roll_loot(loot, depth, &mut rng));
+                remains.push(Remains {
+                    pos: pos.clone(),
+                    renderable: renderables.get(*victim).cloned(),
+                    name: names.get(*victim).map(|name| name.value.clone()),
+                    loot,
+                });
+            }
+        }
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    for remains in remains {
+        if let Some(renderable) = remains.renderable {
+            let name = remains.name.unwrap_or_else(|| "Something".to_string());
+            ecs.create_entity()
+                .with(remains.pos.clone())
+                .with(Renderable {
+                    glyph: rltk::to_cp437('%'),
+                    fg: renderable.fg * CORPSE_BRIGHTNESS,
+                    bg: renderable.bg,
+                    render_order: 3,
+                })
+                .with(Name {
+                    value: format!("{} corpse", name),
+                })
+                .marked::<SimpleMarker<SerializeMe>>()
+                .build();
+        }
+        if let Some(loot) = remains.loot {
+            raws().spawn_named_entity(ecs, &loot, remains.pos.x, remains.pos.y);
+        }
+    }
 }
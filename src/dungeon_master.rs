@@ -0,0 +1,105 @@
+use super::{Map, OtherLevelPosition, Player, Position, TileType, Viewshed};
+use rltk::Point;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Remembers every level the player has left behind. The maps are kept here;
+/// the entities living on them stay in the ECS, but trade their `Position`
+/// for an `OtherLevelPosition` so that no system sees them until the player
+/// comes back.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct DungeonMaster {
+    maps: HashMap<i32, Map>,
+}
+
+impl DungeonMaster {
+    pub fn new() -> DungeonMaster {
+        return DungeonMaster {
+            maps: HashMap::new(),
+        };
+    }
+
+    pub fn store_map(&mut self, map: &Map) {
+        self.maps.insert(map.depth, map.clone());
+    }
+
+    pub fn get_map(&self, depth: i32) -> Option<Map> {
+        return self.maps.get(&depth).cloned();
+    }
+}
+
+/// Stores the current map and parks everything on it, except the player
+pub fn freeze_level(ecs: &mut World) {
+    let map = ecs.fetch::<Map>();
+    ecs.write_resource::<DungeonMaster>().store_map(&map);
+
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let players = ecs.read_storage::<Player>();
+
+    let mut to_park: Vec<Entity> = Vec::new();
+    for (entity, pos, _not_player) in (&entities, &positions, !&players).join() {
+        other_level_positions
+            .insert(
+                entity,
+                OtherLevelPosition {
+                    x: pos.x,
+                    y: pos.y,
+                    depth: map.depth,
+                },
+            )
+            .expect("Unable to insert other level position");
+        to_park.push(entity);
+    }
+
+    for entity in to_park.iter() {
+        positions.remove(*entity);
+    }
+}
+
+/// Makes `map` the current level again, brings back everything that was
+/// parked on it and puts the player on the first `arrive_on` tile
+pub fn thaw_level(ecs: &mut World, mut map: Map, arrive_on: TileType) {
+    map.tile_content = vec![Vec::new(); map.tiles.len()];
+    let depth = map.depth;
+    let arrival_idx = map.tiles.iter().position(|tile| *tile == arrive_on);
+    let arrival = arrival_idx.map(|idx| Point::new(idx as i32 % map.width, idx as i32 / map.width));
+    *ecs.write_resource::<Map>() = map;
+
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+
+    let mut to_restore: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &other_level_positions).join() {
+        if pos.depth == depth {
+            positions
+                .insert(entity, Position { x: pos.x, y: pos.y })
+                .expect("Unable to insert position");
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+            to_restore.push(entity);
+        }
+    }
+
+    for entity in to_restore.iter() {
+        other_level_positions.remove(*entity);
+    }
+
+    if let Some(arrival) = arrival {
+        let player_entity = ecs.fetch::<Entity>();
+        let mut player_pos = ecs.write_resource::<Point>();
+        *player_pos = arrival;
+        if let Some(pos) = positions.get_mut(*player_entity) {
+            pos.x = arrival.x;
+            pos.y = arrival.y;
+        }
+        if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+            viewshed.dirty = true;
+        }
+    }
+}
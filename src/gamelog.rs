@@ -1,3 +1,5 @@
 pub struct GameLog {
     pub entries: Vec<String>,
+    /// Also explain the dice behind every attack
+    pub verbose: bool,
 }
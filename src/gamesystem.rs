@@ -0,0 +1,54 @@
+//! The rules that turn attributes and levels into the numbers combat uses
+
+/// Attribute value an average person has, with no bonus either way
+pub const AVERAGE_ATTRIBUTE: i32 = 10;
+/// Hit points the player has before any levels are counted
+const PLAYER_BASE_HP: i32 = 15;
+/// Hit points gained per level, before the fitness bonus
+const HP_PER_LEVEL: i32 = 10;
+/// Armor class of an average, unarmoured target
+const BASE_ARMOR_CLASS: i32 = 10;
+
+/// The bonus (or penalty) an attribute gives: +1 for every two points above
+/// average, -1 for every two below
+pub fn attr_bonus(value: i32) -> i32 {
+    return (value - AVERAGE_ATTRIBUTE).div_euclid(2);
+}
+
+pub fn player_hp_at_level(fitness: i32, level: i32) -> i32 {
+    return PLAYER_BASE_HP + level * (HP_PER_LEVEL + attr_bonus(fitness));
+}
+
+/// Monsters don't get the player's head start, but always have at least
+/// one hit point
+pub fn npc_hp(fitness: i32, level: i32) -> i32 {
+    return i32::max(1, level * (HP_PER_LEVEL + attr_bonus(fitness)));
+}
+
+/// What an attacker's d20 roll has to reach to hit, before any armour
+pub fn armor_class(quickness: i32) -> i32 {
+    return BASE_ARMOR_CLASS + attr_bonus(quickness);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bonuses_step_every_two_points() {
+        assert_eq!(attr_bonus(10), 0);
+        assert_eq!(attr_bonus(11), 0);
+        assert_eq!(attr_bonus(12), 1);
+        assert_eq!(attr_bonus(9), -1);
+        assert_eq!(attr_bonus(6), -2);
+    }
+
+    #[test]
+    fn hit_points_grow_with_level_and_fitness() {
+        assert_eq!(player_hp_at_level(14, 1), 27);
+        assert_eq!(player_hp_at_level(14, 2), 39);
+        assert_eq!(npc_hp(10, 2), 20);
+        assert_eq!(npc_hp(6, 1), 8);
+        assert_eq!(npc_hp(1, 1), 5);
+    }
+}
@@ -1,6 +1,6 @@
 use super::{
-    CombatStats, Equipped, GameLog, InBackpack, Map, Name, Player, Position, RunState, State,
-    Viewshed,
+    camera, Ammunition, Attribute, Attributes, Equipped, GameLog, Hidden, InBackpack, Map, Name,
+    Player, Pools, Position, RunSeed, RunState, State, Viewshed,
 };
 use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
@@ -12,9 +12,17 @@ pub enum ItemMenuResult {
     Selected,
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum TargetingResult {
+    Cancel,
+    NoResponse { selected: usize },
+    Selected { target: Point },
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuSelection {
     NewGame,
+    SeededGame,
     LoadGame,
     Quit,
 }
@@ -35,18 +43,34 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         RGB::named(rltk::BLACK),
     );
 
-    let combat_stats = ecs.read_component::<CombatStats>();
+    let pools = ecs.read_storage::<Pools>();
+    let attributes = ecs.read_storage::<Attributes>();
     let players = ecs.read_storage::<Player>();
     let log = ecs.fetch::<GameLog>();
     let map = ecs.fetch::<Map>();
+    let seed = ecs.fetch::<RunSeed>();
 
     // print depth
+    let depth = if map.depth == 0 {
+        "Town".to_string()
+    } else {
+        format!("Depth: {}", map.depth)
+    };
     ctx.print_color(
         2,
         43,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        &format!("Depth: {}", map.depth),
+        depth,
+    );
+
+    // print seed, so bug reports can name the exact run
+    ctx.print_color(
+        2,
+        49,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        format!("Seed: {}", seed.value),
     );
 
     // print log
@@ -59,8 +83,9 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     }
 
     // show stats
-    for (_p, stats) in (&players, &combat_stats).join() {
-        let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
+    for (_p, pools, attributes) in (&players, &pools, &attributes).join() {
+        let hit_points = &pools.hit_points;
+        let health = format!(" HP: {} / {} ", hit_points.current, hit_points.max);
         ctx.print_color(
             12,
             43,
@@ -73,11 +98,26 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
             28,
             43,
             51,
-            stats.hp,
-            stats.max_hp,
+            hit_points.current,
+            hit_points.max,
             RGB::named(rltk::RED),
             RGB::named(rltk::BLACK),
-        )
+        );
+
+        ctx.print_color(
+            20,
+            49,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            format!(
+                "Level {}  Mig {} Fit {} Qui {} Int {}",
+                pools.level,
+                attribute_text(&attributes.might),
+                attribute_text(&attributes.fitness),
+                attribute_text(&attributes.quickness),
+                attribute_text(&attributes.intelligence),
+            ),
+        );
     }
 
     // mouse cursor
@@ -86,21 +126,39 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+/// An attribute's value and bonus, such as "14(+2)"
+fn attribute_text(attribute: &Attribute) -> String {
+    return format!("{}({:+})", attribute.value(), attribute.bonus);
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
+    if mouse_pos.0 >= camera::VIEW_WIDTH || mouse_pos.1 >= camera::VIEW_HEIGHT {
+        return;
+    }
+    let mouse_world = camera::screen_to_world(ecs, mouse_pos.0, mouse_pos.1);
+    if mouse_world.x < 0
+        || mouse_world.x >= map.width
+        || mouse_world.y < 0
+        || mouse_world.y >= map.height
+    {
         return;
     }
 
     let mut tooltip: Vec<String> = Vec::new();
 
-    for (name, position) in (&names, &positions).join() {
+    for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == mouse_world.x
+            && position.y == mouse_world.y
+            && map.visible_tiles[idx]
+            && map.is_lit(idx)
+        {
             tooltip.push(name.value.to_string());
         }
     }
@@ -134,7 +192,7 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::SLATE_GREY),
-                        &" ".to_string(),
+                        " ".to_string(),
                     );
                 }
                 y += 1;
@@ -145,7 +203,7 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::SLATE_GREY),
-                &"->".to_string(),
+                "->".to_string(),
             );
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
@@ -167,7 +225,7 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::SLATE_GREY),
-                        &" ".to_string(),
+                        " ".to_string(),
                     );
                 }
                 y += 1;
@@ -178,16 +236,25 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::SLATE_GREY),
-                &"<-".to_string(),
+                "<-".to_string(),
             );
         }
     }
 }
 
+/// An item's name, with how many there are if it comes in a stack
+fn item_label(name: &Name, ammunition: Option<&Ammunition>) -> String {
+    return match ammunition {
+        Some(ammo) => format!("{} ({})", name.value, ammo.count),
+        None => name.value.to_string(),
+    };
+}
+
 pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammunition = gs.ecs.read_storage::<Ammunition>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -247,7 +314,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.value.to_string());
+        ctx.print(21, y, item_label(name, ammunition.get(entity)));
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -276,6 +343,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammunition = gs.ecs.read_storage::<Ammunition>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -301,7 +369,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     );
     ctx.print_color(
         18,
-        y + count as i32 + 1,
+        y + count + 1,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
         "Esc to close",
@@ -335,7 +403,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.value.to_string());
+        ctx.print(21, y, item_label(name, ammunition.get(entity)));
         equippable.push(entity);
 
         y += 1;
@@ -383,7 +451,9 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
+                if let Some(screen) = camera::world_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::BLUE));
+                }
                 available_tiles.push(idx);
             }
         }
@@ -393,19 +463,17 @@ pub fn ranged_target(
 
     // draw cursor with targetting context
     let mouse_pos = ctx.mouse_pos();
+    let mouse_world = camera::screen_to_world(&gs.ecs, mouse_pos.0, mouse_pos.1);
     let mut valid_target = false;
     for idx in available_tiles.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
+        if idx.x == mouse_world.x && idx.y == mouse_world.y {
             valid_target = true;
         }
     }
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, Some(mouse_world));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
@@ -417,6 +485,96 @@ pub fn ranged_target(
     return (ItemMenuResult::NoResponse, None);
 }
 
+/// Aims the equipped ranged weapon. Tab cycles through the creatures in
+/// range, nearest first; Enter or T fires at the selected one, and the mouse
+/// can pick any tile in range.
+pub fn fire_target(gs: &mut State, ctx: &mut Rltk, range: i32, selected: usize) -> TargetingResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let player_pos = gs.ecs.fetch::<Point>();
+    let viewsheds = gs.ecs.read_storage::<Viewshed>();
+    let map = gs.ecs.fetch::<Map>();
+    let pools = gs.ecs.read_storage::<Pools>();
+    let hidden = gs.ecs.read_storage::<Hidden>();
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Select Target (Tab: next, Enter: fire, Esc: cancel)",
+    );
+
+    // Highlight tiles in range, and find who can be shot at
+    let mut available_tiles = Vec::new();
+    let mut targets: Vec<(Point, f32)> = Vec::new();
+    if let Some(visible) = viewsheds.get(*player_entity) {
+        for tile in visible.visible_tiles.iter() {
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *tile);
+            if distance > range as f32 {
+                continue;
+            }
+            if let Some(screen) = camera::world_to_screen(&gs.ecs, *tile) {
+                ctx.set_bg(screen.x, screen.y, RGB::named(rltk::BLUE));
+            }
+            available_tiles.push(*tile);
+
+            let idx = map.xy_idx(tile.x, tile.y);
+            let has_target = map.is_lit(idx)
+                && map.tile_content[idx].iter().any(|entity| {
+                    *entity != *player_entity
+                        && pools.contains(*entity)
+                        && !hidden.contains(*entity)
+                });
+            if has_target {
+                targets.push((*tile, distance));
+            }
+        }
+    } else {
+        return TargetingResult::Cancel;
+    }
+    targets.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let current = if targets.is_empty() {
+        None
+    } else {
+        Some(targets[selected % targets.len()].0)
+    };
+    if let Some(current) = current {
+        if let Some(screen) = camera::world_to_screen(&gs.ecs, current) {
+            ctx.set_bg(screen.x, screen.y, RGB::named(rltk::CYAN));
+        }
+    }
+
+    // the mouse can still pick any tile in range
+    let mouse_pos = ctx.mouse_pos();
+    let mouse_world = camera::screen_to_world(&gs.ecs, mouse_pos.0, mouse_pos.1);
+    if available_tiles.contains(&mouse_world) {
+        ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
+        if ctx.left_click {
+            return TargetingResult::Selected {
+                target: mouse_world,
+            };
+        }
+    } else {
+        ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
+        if ctx.left_click {
+            return TargetingResult::Cancel;
+        }
+    }
+
+    return match ctx.key {
+        Some(VirtualKeyCode::Escape) => TargetingResult::Cancel,
+        Some(VirtualKeyCode::Tab) => TargetingResult::NoResponse {
+            selected: selected + 1,
+        },
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::T) => match current {
+            Some(target) => TargetingResult::Selected { target },
+            None => TargetingResult::NoResponse { selected },
+        },
+        _ => TargetingResult::NoResponse { selected },
+    };
+}
+
 pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     let save_exists = super::saveload_system::does_save_exist();
     let runstate = gs.ecs.fetch::<RunState>();
@@ -440,9 +598,20 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             "New Game",
         );
 
+        ctx.print_color_centered(
+            25,
+            if menu_selection == MainMenuSelection::SeededGame {
+                RGB::named(rltk::MAGENTA)
+            } else {
+                RGB::named(rltk::WHITE)
+            },
+            RGB::named(rltk::BLACK),
+            "New Game From Seed",
+        );
+
         if save_exists {
             ctx.print_color_centered(
-                25,
+                26,
                 if menu_selection == MainMenuSelection::LoadGame {
                     RGB::named(rltk::MAGENTA)
                 } else {
@@ -454,7 +623,7 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         }
 
         ctx.print_color_centered(
-            if save_exists { 26 } else { 25 },
+            if save_exists { 27 } else { 26 },
             if menu_selection == MainMenuSelection::Quit {
                 RGB::named(rltk::MAGENTA)
             } else {
@@ -480,11 +649,14 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                     let mut new_selection;
                     match menu_selection {
                         MainMenuSelection::NewGame => new_selection = MainMenuSelection::Quit,
-                        MainMenuSelection::LoadGame => new_selection = MainMenuSelection::NewGame,
+                        MainMenuSelection::SeededGame => new_selection = MainMenuSelection::NewGame,
+                        MainMenuSelection::LoadGame => {
+                            new_selection = MainMenuSelection::SeededGame
+                        }
                         MainMenuSelection::Quit => new_selection = MainMenuSelection::LoadGame,
                     }
                     if new_selection == MainMenuSelection::LoadGame && !save_exists {
-                        new_selection = MainMenuSelection::NewGame;
+                        new_selection = MainMenuSelection::SeededGame;
                     }
                     return MainMenuResult::NoSelection {
                         selected: new_selection,
@@ -493,7 +665,10 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 VirtualKeyCode::Down => {
                     let mut new_selection;
                     match menu_selection {
-                        MainMenuSelection::NewGame => new_selection = MainMenuSelection::LoadGame,
+                        MainMenuSelection::NewGame => new_selection = MainMenuSelection::SeededGame,
+                        MainMenuSelection::SeededGame => {
+                            new_selection = MainMenuSelection::LoadGame
+                        }
                         MainMenuSelection::LoadGame => new_selection = MainMenuSelection::Quit,
                         MainMenuSelection::Quit => new_selection = MainMenuSelection::NewGame,
                     }
@@ -539,7 +714,7 @@ pub fn remove_equipment_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult,
         15,
         y - 2,
         31,
-        (count + 3) as i32,
+        count + 3,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
     );
@@ -583,7 +758,7 @@ pub fn remove_equipment_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult,
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.value.to_string());
+        ctx.print(21, y, name.value.to_string());
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -607,6 +782,64 @@ pub fn remove_equipment_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult,
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum SeedEntryResult {
+    Cancel,
+    NoResponse { seed: u64 },
+    Selected { seed: u64 },
+}
+
+pub fn enter_seed(ctx: &mut Rltk, seed: u64) -> SeedEntryResult {
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Enter a seed",
+    );
+    ctx.print_color_centered(
+        18,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        format!("{}_", seed),
+    );
+    ctx.print_color_centered(
+        21,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        "Enter to start, Esc to go back",
+    );
+
+    match ctx.key {
+        None => return SeedEntryResult::NoResponse { seed },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => return SeedEntryResult::Cancel,
+            VirtualKeyCode::Return => return SeedEntryResult::Selected { seed },
+            VirtualKeyCode::Back => return SeedEntryResult::NoResponse { seed: seed / 10 },
+            _ => {
+                let digit = match key {
+                    VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+                    VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+                    VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+                    VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+                    VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+                    VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+                    VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+                    VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+                    VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+                    VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+                    _ => None,
+                };
+
+                // ignore digits that would overflow the seed
+                let new_seed = digit.and_then(|d| seed.checked_mul(10)?.checked_add(d));
+                return SeedEntryResult::NoResponse {
+                    seed: new_seed.unwrap_or(seed),
+                };
+            }
+        },
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult {
     NoSelection,
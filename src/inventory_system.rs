@@ -1,7 +1,7 @@
 use super::{
-    AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped, GameLog, InBackpack,
-    InflictsDamage, Map, Name, Position, ProvidesHealing, SufferDamage, WantsToDropItem,
-    WantsToPickUpItem, WantsToRemoveEquipment, WantsToUseItem,
+    Ammunition, AreaOfEffect, Confusion, Consumable, Damage, Equippable, Equipped, GameLog,
+    InBackpack, InflictsDamage, Map, Name, Pools, Position, ProvidesHealing, SufferDamage,
+    WantsToDropItem, WantsToPickUpItem, WantsToRemoveEquipment, WantsToUseItem,
 };
 use specs::prelude::*;
 
@@ -11,17 +11,51 @@ impl<'a> System<'a> for ItemBagSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        Entities<'a>,
         WriteStorage<'a, WantsToPickUpItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Ammunition>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_to_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_to_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut ammunition,
+        ) = data;
 
         for pickup in wants_to_pickup.join() {
+            let name = &names.get(pickup.item).unwrap().value;
+
+            // ammunition joins a stack of the same kind that's already carried
+            if let Some(picked_up) = ammunition.get(pickup.item).map(|ammo| ammo.count) {
+                let stack = (&entities, &backpack, &names)
+                    .join()
+                    .find(|(entity, pack, stack_name)| {
+                        pack.owner == pickup.collected_by
+                            && stack_name.value == *name
+                            && ammunition.contains(*entity)
+                    })
+                    .map(|(entity, _pack, _name)| entity);
+                if let Some(stack) = stack {
+                    ammunition.get_mut(stack).unwrap().count += picked_up;
+                    entities.delete(pickup.item).expect("Delete failed");
+                    if pickup.collected_by == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("You pick up {} {}.", picked_up, name));
+                    }
+                    continue;
+                }
+            }
+
             positions.remove(pickup.item);
             backpack
                 .insert(
@@ -33,10 +67,7 @@ impl<'a> System<'a> for ItemBagSystem {
                 .expect("Unable to insert backpack entry");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!(
-                    "You pick up the {}.",
-                    names.get(pickup.item).unwrap().value
-                ));
+                gamelog.entries.push(format!("You pick up the {}.", name));
             }
         }
 
@@ -58,7 +89,7 @@ impl<'a> System<'a> for ItemUseSystem {
         ReadStorage<'a, InflictsDamage>,
         ReadStorage<'a, AreaOfEffect>,
         WriteStorage<'a, Confusion>,
-        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, Pools>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, Consumable>,
         ReadStorage<'a, Equippable>,
@@ -78,7 +109,7 @@ impl<'a> System<'a> for ItemUseSystem {
             damaging_items,
             aoe_items,
             mut confusions,
-            mut combat_stats,
+            mut pools,
             mut suffer_damage,
             consumables,
             equippable_items,
@@ -171,8 +202,8 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(heal) => {
                     for target in targets.iter() {
-                        if let Some(stats) = combat_stats.get_mut(*target) {
-                            stats.hp = i32::min(stats.max_hp, stats.hp + heal.heal_amount);
+                        if let Some(target_pools) = pools.get_mut(*target) {
+                            target_pools.hit_points.restore(heal.heal_amount);
                             if entity == *player_entity {
                                 gamelog.entries.push(format!(
                                     "You use the {}, healing for {}.",
@@ -190,14 +221,18 @@ impl<'a> System<'a> for ItemUseSystem {
             match damaging_item {
                 None => {}
                 Some(damage) => {
+                    let item_name = &names.get(item_used.item).unwrap().value;
                     for target in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *target, damage.damage);
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            *target,
+                            Damage::new(damage.damage, damage.damage_type, item_name),
+                        );
                         if entity == *player_entity {
                             gamelog.entries.push(format!(
-                                "You use the {} on {}, dealing {} damage.",
-                                names.get(item_used.item).unwrap().value,
-                                names.get(*target).unwrap().value,
-                                damage.damage
+                                "You use the {} on {}.",
+                                item_name,
+                                names.get(*target).unwrap().value
                             ));
                         }
                         item_is_used = true;
@@ -0,0 +1,20 @@
+#![allow(clippy::needless_return, clippy::explicit_counter_loop)]
+//! Everything needed to generate a level without opening a window.
+//! Shared by the game and the `mapgen` tool.
+extern crate serde;
+pub mod combat;
+pub mod components;
+pub use components::*;
+pub mod dungeon_master;
+pub use dungeon_master::DungeonMaster;
+pub mod gamesystem;
+pub mod map;
+pub use map::*;
+pub mod map_builders;
+pub mod random_table;
+pub mod raws;
+pub mod rect;
+pub use rect::Rect;
+pub mod run_seed;
+pub use run_seed::RunSeed;
+pub mod spawner;
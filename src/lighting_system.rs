@@ -0,0 +1,41 @@
+use super::{LightSource, Map, Position};
+use rltk::{field_of_view, Point};
+use specs::prelude::*;
+
+/// How much of its brightness a light has lost by the edge of its range
+const LIGHT_FALLOFF: f32 = 0.75;
+
+/// Recalculates how brightly lit every tile is, from the level's ambient light
+/// and every light source on it
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, light_sources) = data;
+
+        let ambient_light = map.ambient_light;
+        for light in map.light.iter_mut() {
+            *light = ambient_light;
+        }
+
+        for (pos, light_source) in (&positions, &light_sources).join() {
+            let origin = Point::new(pos.x, pos.y);
+            let lit_tiles = field_of_view(origin, light_source.range, &*map);
+            for tile in lit_tiles.iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, *tile);
+                let intensity = 1.0 - LIGHT_FALLOFF * distance / light_source.range as f32;
+                let idx = map.xy_idx(tile.x, tile.y);
+                map.light[idx] = f32::max(map.light[idx], intensity);
+            }
+        }
+    }
+}
@@ -1,12 +1,17 @@
+#![allow(clippy::needless_return, clippy::explicit_counter_loop)]
+use hellorust::{
+    combat, components, dungeon_master, map, map_builders, raws, rect, run_seed, spawner,
+};
 use rltk::{GameState, Point, Rltk};
 use specs::prelude::*;
-mod components;
+mod attribute_system;
+use attribute_system::AttributeSystem;
+mod camera;
 pub use components::*;
-mod map;
+pub use dungeon_master::DungeonMaster;
 pub use map::*;
 mod player;
 use player::*;
-mod rect;
 pub use rect::Rect;
 mod visibility_system;
 use visibility_system::VisibilitySystem;
@@ -16,20 +21,26 @@ mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
 mod damage_system;
 use damage_system::DamageSystem;
 mod gamelog;
 use gamelog::GameLog;
 mod gui;
 mod inventory_system;
-mod spawner;
+mod lighting_system;
+mod terrain_system;
+mod trigger_system;
 use inventory_system::EquipmentRemoveSystem;
 use inventory_system::ItemBagSystem;
 use inventory_system::ItemDropSystem;
 use inventory_system::ItemUseSystem;
-extern crate serde;
+use lighting_system::LightingSystem;
+use run_seed::RunSeed;
 use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
-mod random_table;
+use terrain_system::TerrainSystem;
+use trigger_system::TriggerSystem;
 mod saveload_system;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -44,17 +55,34 @@ pub enum RunState {
         range: i32,
         item: Entity,
     },
+    /// Aiming the equipped ranged weapon; `selected` is the target that
+    /// Tab has cycled to
+    ShowFireTargeting {
+        range: i32,
+        selected: usize,
+    },
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
+    EnterSeed {
+        seed: u64,
+    },
     SaveGame,
     NextLevel,
+    PreviousLevel,
     ShowRemoveEquipment,
     GameOver,
+    MapGeneration,
 }
 
+/// How long each map generation snapshot stays on screen, in milliseconds
+const MAPGEN_FRAME_TIME: f32 = 50.0;
+
 pub struct State {
     pub ecs: World,
+    mapgen_history: Vec<Vec<TileType>>,
+    mapgen_index: usize,
+    mapgen_timer: f32,
 }
 
 impl State {
@@ -65,8 +93,14 @@ impl State {
         mob.run_now(&self.ecs);
         let mut mapindex = MapIndexingSystem {};
         mapindex.run_now(&self.ecs);
+        let mut triggers = TriggerSystem {};
+        triggers.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
+        let mut ranged = RangedCombatSystem {};
+        ranged.run_now(&self.ecs);
+        let mut terrain = TerrainSystem {};
+        terrain.run_now(&self.ecs);
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
         let mut inventory = ItemBagSystem {};
@@ -77,19 +111,27 @@ impl State {
         drop_item.run_now(&self.ecs);
         let mut equipment_remove = EquipmentRemoveSystem {};
         equipment_remove.run_now(&self.ecs);
+        let mut attributes = AttributeSystem {};
+        attributes.run_now(&self.ecs);
+        let mut lighting = LightingSystem {};
+        lighting.run_now(&self.ecs);
 
         self.ecs.maintain();
     }
 
     fn register_components(&mut self) {
         self.ecs.register::<Position>();
+        self.ecs.register::<OtherLevelPosition>();
         self.ecs.register::<Renderable>();
         self.ecs.register::<Player>();
         self.ecs.register::<Viewshed>();
         self.ecs.register::<Monster>();
+        self.ecs.register::<Faction>();
+        self.ecs.register::<Wading>();
         self.ecs.register::<Name>();
         self.ecs.register::<BlocksTile>();
-        self.ecs.register::<CombatStats>();
+        self.ecs.register::<Attributes>();
+        self.ecs.register::<Pools>();
         self.ecs.register::<WantsToMelee>();
         self.ecs.register::<SufferDamage>();
         self.ecs.register::<Item>();
@@ -103,12 +145,25 @@ impl State {
         self.ecs.register::<InflictsDamage>();
         self.ecs.register::<AreaOfEffect>();
         self.ecs.register::<Confusion>();
+        self.ecs.register::<Hidden>();
+        self.ecs.register::<EntryTrigger>();
+        self.ecs.register::<EntityMoved>();
+        self.ecs.register::<SingleActivation>();
+        self.ecs.register::<TeleportsTarget>();
+        self.ecs.register::<LightSource>();
+        self.ecs.register::<LootTable>();
         self.ecs.register::<SimpleMarker<SerializeMe>>();
         self.ecs.register::<SerializationHelper>();
         self.ecs.register::<Equippable>();
         self.ecs.register::<Equipped>();
-        self.ecs.register::<MeleePowerBonus>();
+        self.ecs.register::<MeleeWeapon>();
+        self.ecs.register::<NaturalAttack>();
+        self.ecs.register::<Resistances>();
+        self.ecs.register::<RangedWeapon>();
+        self.ecs.register::<Ammunition>();
+        self.ecs.register::<WantsToShoot>();
         self.ecs.register::<DefenseBonus>();
+        self.ecs.register::<AttributeBonus>();
         self.ecs.register::<WantsToRemoveEquipment>();
     }
 }
@@ -125,27 +180,12 @@ impl GameState for State {
 
         match newrunstate {
             RunState::MainMenu { .. } => {}
-            RunState::GameOver { .. } => {}
+            RunState::EnterSeed { .. } => {}
+            RunState::GameOver => {}
+            RunState::MapGeneration => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
-                        }
-                    }
-
-                    gui::draw_ui(&self.ecs, ctx);
-                }
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
             }
         }
 
@@ -219,6 +259,21 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowFireTargeting { range, selected } => {
+                match gui::fire_target(self, ctx, range, selected) {
+                    gui::TargetingResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::TargetingResult::NoResponse { selected } => {
+                        newrunstate = RunState::ShowFireTargeting { range, selected }
+                    }
+                    gui::TargetingResult::Selected { target } => {
+                        let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                        intent
+                            .insert(*self.ecs.fetch::<Entity>(), WantsToShoot { target })
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                }
+            }
             RunState::ShowTargeting { range, item } => {
                 let result = gui::ranged_target(self, ctx, range);
                 match result.0 {
@@ -248,7 +303,13 @@ impl GameState for State {
                         }
                     }
                     gui::MainMenuResult::Selected { selected } => match selected {
-                        gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                        gui::MainMenuSelection::NewGame => {
+                            self.new_game(RunSeed::random());
+                            newrunstate = RunState::MapGeneration;
+                        }
+                        gui::MainMenuSelection::SeededGame => {
+                            newrunstate = RunState::EnterSeed { seed: 0 }
+                        }
                         gui::MainMenuSelection::LoadGame => {
                             saveload_system::load_game(&mut self.ecs);
                             newrunstate = RunState::AwaitingInput;
@@ -258,6 +319,23 @@ impl GameState for State {
                     },
                 }
             }
+            RunState::EnterSeed { seed } => {
+                let result = gui::enter_seed(ctx, seed);
+                match result {
+                    gui::SeedEntryResult::Cancel => {
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::SeededGame,
+                        }
+                    }
+                    gui::SeedEntryResult::NoResponse { seed } => {
+                        newrunstate = RunState::EnterSeed { seed }
+                    }
+                    gui::SeedEntryResult::Selected { seed } => {
+                        self.new_game(RunSeed { value: seed });
+                        newrunstate = RunState::MapGeneration;
+                    }
+                }
+            }
             RunState::SaveGame => {
                 saveload_system::save_game(&mut self.ecs);
                 newrunstate = RunState::MainMenu {
@@ -265,8 +343,12 @@ impl GameState for State {
                 };
             }
             RunState::NextLevel => {
-                self.goto_next_level();
-                newrunstate = RunState::PreRun;
+                self.goto_level(1);
+                newrunstate = RunState::MapGeneration;
+            }
+            RunState::PreviousLevel => {
+                self.goto_level(-1);
+                newrunstate = RunState::MapGeneration;
             }
             RunState::ShowRemoveEquipment => {
                 let result = gui::remove_equipment_menu(self, ctx);
@@ -287,12 +369,40 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::MapGeneration => {
+                if !map_builders::SHOW_MAPGEN_VISUALIZER {
+                    newrunstate = RunState::PreRun;
+                } else {
+                    if self.mapgen_index < self.mapgen_history.len() {
+                        let mut snapshot = Map::clone(&self.ecs.fetch::<Map>());
+                        snapshot.tiles = self.mapgen_history[self.mapgen_index].clone();
+                        for (revealed, visible) in snapshot
+                            .revealed_tiles
+                            .iter_mut()
+                            .zip(snapshot.visible_tiles.iter_mut())
+                        {
+                            *revealed = true;
+                            *visible = true;
+                        }
+                        snapshot.light = vec![1.0; snapshot.tiles.len()];
+                        camera::render_debug_map(&snapshot, ctx);
+                    }
+
+                    self.mapgen_timer += ctx.frame_time_ms;
+                    if self.mapgen_timer > MAPGEN_FRAME_TIME {
+                        self.mapgen_timer = 0.0;
+                        self.mapgen_index += 1;
+                    }
+                    if self.mapgen_index >= self.mapgen_history.len() {
+                        newrunstate = RunState::PreRun;
+                    }
+                }
+            }
             RunState::GameOver => {
                 let result = gui::game_over(ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
-                        self.game_over_cleanup();
                         newrunstate = RunState::MainMenu {
                             menu_selection: gui::MainMenuSelection::NewGame,
                         };
@@ -311,95 +421,63 @@ impl GameState for State {
 }
 
 impl State {
-    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
-        let entities = self.ecs.entities();
-        let player = self.ecs.read_storage::<Player>();
-        let backpack = self.ecs.read_storage::<InBackpack>();
-        let player_entity = self.ecs.fetch::<Entity>();
-        let equipped = self.ecs.read_storage::<Equipped>();
-
-        let mut to_delete: Vec<Entity> = Vec::new();
-        for entity in entities.join() {
-            let mut should_delete = true;
-
-            if let Some(_p) = player.get(entity) {
-                should_delete = false;
-            }
-            if let Some(backpack) = backpack.get(entity) {
-                if backpack.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
-            if let Some(equipment) = equipped.get(entity) {
-                if equipment.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
-
-            if should_delete {
-                to_delete.push(entity);
-            }
-        }
-
-        return to_delete;
-    }
-
-    fn goto_next_level(&mut self) {
-        let to_delete = self.entities_to_remove_on_level_change();
-        for entity in to_delete {
-            self.ecs
-                .delete_entity(entity)
-                .expect("Unable to delete entity");
+    /// Moves the player `offset` floors down (or up, if negative). The level
+    /// being left is handed to the dungeon master, and levels visited before
+    /// come back exactly as they were left.
+    fn goto_level(&mut self, offset: i32) {
+        dungeon_master::freeze_level(&mut self.ecs);
+
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        let new_depth = current_depth + offset;
+        let stored_map = self.ecs.fetch::<DungeonMaster>().get_map(new_depth);
+
+        if let Some(map) = stored_map {
+            self.mapgen_history.clear();
+            self.mapgen_index = 0;
+            self.mapgen_timer = 0.0;
+
+            let arrive_on = if offset < 0 {
+                TileType::StairsDown
+            } else {
+                TileType::StairsUp
+            };
+            dungeon_master::thaw_level(&mut self.ecs, map, arrive_on);
+
+            let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+            let verb = if offset < 0 {
+                "climb back up"
+            } else {
+                "return down"
+            };
+            gamelog
+                .entries
+                .push(format!("You {} to floor {}.", verb, new_depth));
+            return;
         }
 
-        let world_map;
-        let current_depth;
-        {
-            let mut world_map_resource = self.ecs.write_resource::<Map>();
-            current_depth = world_map_resource.depth;
-            *world_map_resource = Map::new_map_rooms_and_corridors(current_depth + 1);
-            world_map = world_map_resource.clone();
-        }
-
-        // spawn enemies
-        for room in world_map.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
-        }
+        self.generate_world_map(new_depth);
 
-        // place the player
-        let (p_x, p_y) = world_map.rooms[0].center();
-        let mut player_pos = self.ecs.write_resource::<Point>();
-        *player_pos = Point::new(p_x, p_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
         let player_entity = self.ecs.fetch::<Entity>();
-        if let Some(p_pos_comp) = position_components.get_mut(*player_entity) {
-            p_pos_comp.x = p_x;
-            p_pos_comp.y = p_y;
-        }
-
-        // mark viewsheds for update
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        if let Some(viewshed) = viewshed_components.get_mut(*player_entity) {
-            viewshed.dirty = true;
-        }
 
         // heal the Player
-        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
+        let mut player_pools_store = self.ecs.write_storage::<Pools>();
         let mut amount_healed: i32 = 0;
-        if let Some(player_health) = player_health_store.get_mut(*player_entity) {
-            amount_healed = player_health.max_hp / 2;
-            player_health.hp += amount_healed;
+        if let Some(player_pools) = player_pools_store.get_mut(*player_entity) {
+            let hit_points = &mut player_pools.hit_points;
+            amount_healed = i32::min(hit_points.max / 2, hit_points.max - hit_points.current);
+            hit_points.restore(amount_healed);
         }
 
         // write to the log
         let mut gamelog = self.ecs.fetch_mut::<GameLog>();
         gamelog.entries.push(format!(
             "You descend to floor {}, and heal {} hp.",
-            world_map.depth, amount_healed,
+            new_depth, amount_healed,
         ));
     }
 
-    fn game_over_cleanup(&mut self) {
+    /// Throws away the current run and starts a fresh one from the given seed
+    fn new_game(&mut self, seed: RunSeed) {
         let mut to_delete = Vec::new();
         for entity in self.ecs.entities().join() {
             to_delete.push(entity);
@@ -408,34 +486,57 @@ impl State {
             self.ecs.delete_entity(*entity).expect("Deletion failed");
         }
 
-        let world_map;
+        // reseed the game's own dice, so combat plays out the same way too
+        self.ecs
+            .insert(rltk::RandomNumberGenerator::seeded(seed.value));
+        self.ecs.insert(seed);
+        self.ecs.insert(DungeonMaster::new());
+        self.ecs.insert(GameLog {
+            entries: vec!["Welcome to Generic Roguelike".to_string()],
+            verbose: false,
+        });
+
+        // make new player
+        {
+            let player_entity = spawner::player(&mut self.ecs, 0, 0);
+            let mut player_entity_writer = self.ecs.write_resource::<Entity>();
+            *player_entity_writer = player_entity;
+        }
+
+        self.generate_world_map(0);
+    }
+
+    /// Builds a new level for the given depth, spawns its inhabitants and
+    /// moves the player to the builder's starting position
+    fn generate_world_map(&mut self, new_depth: i32) {
+        let mut rng = self.ecs.fetch::<RunSeed>().level_rng(new_depth);
+        let mut builder = map_builders::random_builder(new_depth, &mut rng);
+        builder.build_map(&mut rng);
+        self.mapgen_history = builder.get_snapshot_history();
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
         {
             let mut world_map_resource = self.ecs.write_resource::<Map>();
-            *world_map_resource = Map::new_map_rooms_and_corridors(1);
-            world_map = world_map_resource.clone()
+            *world_map_resource = builder.get_map();
         }
 
         // spawn enemies
-        for room in world_map.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
-        }
+        builder.spawn_entities(&mut self.ecs);
 
-        // make new player
-        let (p_x, p_y) = world_map.rooms[0].center();
-        let player_entity = spawner::player(&mut self.ecs, p_x, p_y);
+        // place the player
+        let player_start = builder.get_starting_position();
         let mut player_pos = self.ecs.write_resource::<Point>();
-        *player_pos = Point::new(p_x, p_y);
+        *player_pos = Point::new(player_start.x, player_start.y);
         let mut position_components = self.ecs.write_storage::<Position>();
-        let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-        *player_entity_writer = player_entity;
-        if let Some(p_pos_comp) = position_components.get_mut(player_entity) {
-            p_pos_comp.x = p_x;
-            p_pos_comp.y = p_y;
+        let player_entity = self.ecs.fetch::<Entity>();
+        if let Some(p_pos_comp) = position_components.get_mut(*player_entity) {
+            p_pos_comp.x = player_start.x;
+            p_pos_comp.y = player_start.y;
         }
 
         // mark viewshed for update
         let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        if let Some(viewshed) = viewshed_components.get_mut(player_entity) {
+        if let Some(viewshed) = viewshed_components.get_mut(*player_entity) {
             viewshed.dirty = true;
         }
     }
@@ -443,31 +544,36 @@ impl State {
 
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
+    // a mistake in the raws should stop the game now, not when a level happens to roll it
+    raws::raws();
+
     let mut context = RltkBuilder::simple80x50()
         .with_title("Generic Roguelike")
         .build()?;
     context.with_post_scanlines(true);
-    let mut gs = State { ecs: World::new() };
+    let mut gs = State {
+        ecs: World::new(),
+        mapgen_history: Vec::new(),
+        mapgen_index: 0,
+        mapgen_timer: 0.0,
+    };
     gs.register_components();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 
-    let map: Map = Map::new_map_rooms_and_corridors(1);
-    let (player_x, player_y) = map.rooms[0].center();
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
+    gs.ecs
+        .insert(Map::new(1, camera::VIEW_WIDTH, camera::VIEW_HEIGHT));
+    gs.ecs.insert(Point::new(0, 0));
+    gs.ecs.insert(RunSeed::random());
+    gs.ecs.insert(DungeonMaster::new());
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
-
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room, 1);
-    }
-
+    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
+    gs.ecs.insert(player_entity);
     gs.ecs.insert(GameLog {
-        entries: vec!["Welcome to Generic Roguelike".to_string()],
+        entries: Vec::new(),
+        verbose: false,
     });
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(player_entity);
+
     gs.ecs.insert(RunState::MainMenu {
         menu_selection: gui::MainMenuSelection::NewGame,
     });
@@ -1,20 +1,51 @@
 use super::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
+use rltk::{Algorithm2D, BaseMap, Point, Rltk, RGB};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub const MAPWIDTH: usize = 80;
-pub const MAPHEIGHT: usize = 43;
-pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
 pub const MAX_MONSTERS: i32 = 4;
 pub const MAX_ITEMS: i32 = 2;
 
-#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+/// Tiles with less light than this are too dark to make out who is in them
+pub const MIN_VISIBLE_LIGHT: f32 = 0.25;
+/// Ambient light on the first level of the dungeon
+const FIRST_LEVEL_LIGHT: f32 = 0.4;
+/// How much darker each level is than the one above it
+const LIGHT_FALLOFF_PER_LEVEL: f32 = 0.05;
+/// Tiles in sight are never drawn darker than this, so the layout can still be made out
+const MIN_TILE_BRIGHTNESS: f32 = 0.15;
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     StairsDown,
+    StairsUp,
+    DoorClosed,
+    DoorOpen,
+    /// Looks like wall to the player until found, but monsters know it's there
+    SecretDoor,
+    Road,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Bridge,
+}
+
+impl TileType {
+    /// Nothing can swim yet, so deep water stops everyone just like a wall
+    pub fn is_walkable(self) -> bool {
+        return !matches!(self, TileType::Wall | TileType::DeepWater);
+    }
+
+    /// How much stepping onto this tile costs when planning a path
+    pub fn path_cost(self) -> f32 {
+        return match self {
+            TileType::ShallowWater => 2.0,
+            TileType::Lava => 10.0,
+            _ => 1.0,
+        };
+    }
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -27,6 +58,10 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked_tiles: Vec<bool>,
     pub depth: i32,
+    /// Light that reaches every tile, before any light sources are added
+    pub ambient_light: f32,
+    /// How brightly lit each tile is, from 0.0 (pitch black) to 1.0
+    pub light: Vec<f32>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -38,33 +73,6 @@ impl Map {
         return (y as usize * self.width as usize) + x as usize;
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
-    }
-
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
-    }
-
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
-    }
-
     fn is_exit_valid(&self, x: i32, y: i32) -> bool {
         if x < 1 || x > self.width - 1 || y < 1 || y > self.height - 1 {
             return false;
@@ -73,79 +81,66 @@ impl Map {
         return !self.blocked_tiles[idx];
     }
 
+    /// Closed doors are left unblocked so paths can run through them; whoever
+    /// walks into one opens it instead of moving
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked_tiles[i] = *tile == TileType::Wall;
+            self.blocked_tiles[i] = !tile.is_walkable();
         }
     }
 
+    /// Cost of a step onto `idx`, scaled by the terrain there
+    fn step_cost(&self, idx: usize, base_cost: f32) -> f32 {
+        return base_cost * self.tiles[idx].path_cost();
+    }
+
     pub fn clear_content_index(&mut self) {
         for content in self.tile_content.iter_mut() {
             content.clear();
         }
     }
 
-    pub fn new_map_rooms_and_corridors(new_depth: i32) -> Map {
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
+    /// Generates an empty map of the given size, consisting entirely of solid walls
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let map_tile_count = (width * height) as usize;
+        let ambient_light = ambient_light(new_depth);
+        return Map {
+            tiles: vec![TileType::Wall; map_tile_count],
             rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked_tiles: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
+            width,
+            height,
+            revealed_tiles: vec![false; map_tile_count],
+            visible_tiles: vec![false; map_tile_count],
+            blocked_tiles: vec![false; map_tile_count],
+            tile_content: vec![Vec::new(); map_tile_count],
             depth: new_depth,
+            ambient_light,
+            light: vec![ambient_light; map_tile_count],
         };
+    }
 
-        const MAX_ROOMS: i32 = 30;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _i in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    ok = false;
-                }
-            }
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
-            }
-        }
-
-        let stairs_pos = map.rooms[map.rooms.len() - 1].center();
-        let stairs_idx = map.xy_idx(stairs_pos.0, stairs_pos.1);
-        map.tiles[stairs_idx] = TileType::StairsDown;
+    /// Whether there is enough light on the tile to see who is standing in it
+    pub fn is_lit(&self, idx: usize) -> bool {
+        return self.light[idx] >= MIN_VISIBLE_LIGHT;
+    }
+}
 
-        return map;
+/// The town lies in daylight; the dungeon gets darker the deeper it goes,
+/// starting out just bright enough to see by
+fn ambient_light(depth: i32) -> f32 {
+    if depth == 0 {
+        return 1.0;
     }
+    let falloff = LIGHT_FALLOFF_PER_LEVEL * (depth - 1) as f32;
+    return f32::max(0.0, FIRST_LEVEL_LIGHT - falloff);
 }
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        return self.tiles[idx] == TileType::Wall;
+        return matches!(
+            self.tiles[idx],
+            TileType::Wall | TileType::DoorClosed | TileType::SecretDoor
+        );
     }
 
     fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
@@ -163,30 +158,30 @@ impl BaseMap for Map {
 
         // Cardinal directions
         if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
+            exits.push((idx - 1, self.step_cost(idx - 1, 1.0)))
         }
         if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
+            exits.push((idx + 1, self.step_cost(idx + 1, 1.0)))
         }
         if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
+            exits.push((idx - w, self.step_cost(idx - w, 1.0)))
         }
         if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
+            exits.push((idx + w, self.step_cost(idx + w, 1.0)))
         }
 
         // Diagonals
         if self.is_exit_valid(x - 1, y - 1) {
-            exits.push(((idx - w) - 1, 2.0));
+            exits.push(((idx - w) - 1, self.step_cost((idx - w) - 1, 2.0)));
         }
         if self.is_exit_valid(x + 1, y - 1) {
-            exits.push(((idx - w) + 1, 2.0));
+            exits.push(((idx - w) + 1, self.step_cost((idx - w) + 1, 2.0)));
         }
         if self.is_exit_valid(x - 1, y + 1) {
-            exits.push(((idx + w) - 1, 2.0));
+            exits.push(((idx + w) - 1, self.step_cost((idx + w) - 1, 2.0)));
         }
         if self.is_exit_valid(x + 1, y + 1) {
-            exits.push(((idx + w) + 1, 2.0));
+            exits.push(((idx + w) + 1, self.step_cost((idx + w) + 1, 2.0)));
         }
 
         return exits;
@@ -199,43 +194,91 @@ impl Algorithm2D for Map {
     }
 }
 
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-
-    let mut y = 0;
-    let mut x = 0;
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending upon the tile type
-
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            match tile {
-                TileType::Floor => {
-                    glyph = 46; // period
-                    fg = RGB::from_f32(0.0, 0.25, 0.1);
-                }
-                TileType::Wall => {
-                    glyph = 35; // hashtag
-                    fg = RGB::from_f32(0.0, 0.8, 0.0);
+/// Draws the part of the map that fits on screen, with `origin` being the
+/// world position of the top-left corner of the screen
+pub fn draw_map(map: &Map, origin: Point, view_width: i32, view_height: i32, ctx: &mut Rltk) {
+    for screen_y in 0..view_height {
+        for screen_x in 0..view_width {
+            let x = origin.x + screen_x;
+            let y = origin.y + screen_y;
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            // Render a tile depending upon the tile type
+            let idx = map.xy_idx(x, y);
+            if map.revealed_tiles[idx] {
+                let glyph;
+                let mut fg;
+                match map.tiles[idx] {
+                    TileType::Floor => {
+                        glyph = 46; // period
+                        fg = RGB::from_f32(0.0, 0.25, 0.1);
+                    }
+                    TileType::Wall | TileType::SecretDoor => {
+                        glyph = 35; // hashtag
+                        fg = RGB::from_f32(0.0, 0.8, 0.0);
+                    }
+                    TileType::StairsDown => {
+                        glyph = 31; // down arrow
+                        fg = RGB::from_f32(0.0, 1.0, 1.0);
+                    }
+                    TileType::StairsUp => {
+                        glyph = 30; // up arrow
+                        fg = RGB::from_f32(0.0, 1.0, 1.0);
+                    }
+                    TileType::DoorClosed => {
+                        glyph = 43; // plus
+                        fg = RGB::named(rltk::CHOCOLATE);
+                    }
+                    TileType::DoorOpen => {
+                        glyph = 39; // apostrophe
+                        fg = RGB::named(rltk::CHOCOLATE);
+                    }
+                    TileType::Road => {
+                        glyph = 46; // period
+                        fg = RGB::from_f32(0.5, 0.4, 0.3);
+                    }
+                    TileType::ShallowWater => {
+                        glyph = 247; // double tilde
+                        fg = RGB::from_f32(0.3, 0.6, 1.0);
+                    }
+                    TileType::DeepWater => {
+                        glyph = 247; // double tilde
+                        fg = RGB::from_f32(0.1, 0.2, 0.8);
+                    }
+                    TileType::Lava => {
+                        glyph = 247; // double tilde
+                        fg = RGB::from_f32(1.0, 0.3, 0.0);
+                    }
+                    TileType::Bridge => {
+                        glyph = 61; // equals
+                        fg = RGB::named(rltk::CHOCOLATE);
+                    }
                 }
-                TileType::StairsDown => {
-                    glyph = 31; // down arrow
-                    fg = RGB::from_f32(0.0, 1.0, 1.0);
+                if map.visible_tiles[idx] {
+                    fg = fg * f32::max(map.light[idx], MIN_TILE_BRIGHTNESS);
+                } else {
+                    fg = fg.to_greyscale()
                 }
-            }
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
-            }
 
-            ctx.set(x, y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
+                ctx.set(screen_x, screen_y, fg, RGB::from_f32(0.0, 0.0, 0.0), glyph);
+            }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Move the coordinates
-        x += 1;
-        if x > MAPWIDTH as i32 - 1 {
-            x = 0;
-            y += 1;
+    #[test]
+    fn the_dungeon_darkens_gradually_from_a_lit_first_level() {
+        assert!(ambient_light(1) >= MIN_VISIBLE_LIGHT);
+        assert!(ambient_light(2) >= MIN_VISIBLE_LIGHT);
+        for depth in 1..10 {
+            assert!(ambient_light(depth + 1) < ambient_light(depth) || ambient_light(depth) == 0.0);
         }
+        assert!(ambient_light(6) < MIN_VISIBLE_LIGHT);
     }
 }
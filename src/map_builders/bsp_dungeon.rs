@@ -0,0 +1,150 @@
+use super::{
+    add_doors, apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, MapBuilder,
+    SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{spawner, Map, Position, Rect, TileType};
+use rltk::RandomNumberGenerator;
+
+const MIN_LEAF_SIZE: i32 = 8;
+const MAX_LEAF_SIZE: i32 = 16;
+const MIN_ROOM_SIZE: i32 = 4;
+
+/// Binary space partition generator: splits the map into leaves, puts a room
+/// in every leaf and joins each pair of siblings with a corridor
+pub struct BspDungeonBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        let root = Rect {
+            x1: 0,
+            y1: 0,
+            x2: self.map.width - 1,
+            y2: self.map.height - 1,
+        };
+        self.partition(rng, root);
+        add_doors(&mut self.map, rng);
+        self.take_snapshot();
+
+        let stairs_pos = self.map.rooms[self.map.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_pos.0, stairs_pos.1);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        let start_pos = self.map.rooms[0].center();
+        self.starting_position = Position {
+            x: start_pos.0,
+            y: start_pos.1,
+        };
+
+        for room in self.map.rooms.iter().skip(1) {
+            spawner::spawn_room(&self.map, rng, room, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl BspDungeonBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BspDungeonBuilder {
+        return BspDungeonBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        };
+    }
+
+    /// Recursively splits `area` until the leaves are small enough to hold a
+    /// single room. Returns one of the rooms placed inside `area`, so the
+    /// caller can connect it to its sibling.
+    fn partition(&mut self, rng: &mut RandomNumberGenerator, area: Rect) -> Rect {
+        let width = area.x2 - area.x1;
+        let height = area.y2 - area.y1;
+        let can_split_x = width > MAX_LEAF_SIZE;
+        let can_split_y = height > MAX_LEAF_SIZE;
+
+        if !can_split_x && !can_split_y {
+            return self.add_leaf_room(rng, area);
+        }
+
+        let split_x = if can_split_x && can_split_y {
+            rng.range(0, width + height) < width
+        } else {
+            can_split_x
+        };
+
+        let (first, second) = if split_x {
+            let at = area.x1 + rng.range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+            (Rect { x2: at, ..area }, Rect { x1: at, ..area })
+        } else {
+            let at = area.y1 + rng.range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+            (Rect { y2: at, ..area }, Rect { y1: at, ..area })
+        };
+
+        let first_room = self.partition(rng, first);
+        let second_room = self.partition(rng, second);
+
+        // connect the siblings
+        let (first_x, first_y) = first_room.center();
+        let (second_x, second_y) = second_room.center();
+        if rng.range(0, 2) == 1 {
+            apply_horizontal_tunnel(&mut self.map, first_x, second_x, first_y);
+            apply_vertical_tunnel(&mut self.map, first_y, second_y, second_x);
+        } else {
+            apply_vertical_tunnel(&mut self.map, first_y, second_y, first_x);
+            apply_horizontal_tunnel(&mut self.map, first_x, second_x, second_y);
+        }
+        self.take_snapshot();
+
+        if rng.range(0, 2) == 1 {
+            return first_room;
+        }
+        return second_room;
+    }
+
+    /// Places a randomly sized room inside the leaf, leaving the leaf's right
+    /// and bottom edges as wall so neighbouring rooms never merge
+    fn add_leaf_room(&mut self, rng: &mut RandomNumberGenerator, leaf: Rect) -> Rect {
+        let leaf_width = leaf.x2 - leaf.x1;
+        let leaf_height = leaf.y2 - leaf.y1;
+
+        let w = rng.range(MIN_ROOM_SIZE, leaf_width);
+        let h = rng.range(MIN_ROOM_SIZE, leaf_height);
+        let x = leaf.x1 + rng.range(0, leaf_width - w);
+        let y = leaf.y1 + rng.range(0, leaf_height - h);
+
+        let room = Rect::new(x, y, w, h);
+        apply_room_to_map(&mut self.map, &room);
+        self.map.rooms.push(room);
+        self.take_snapshot();
+
+        return room;
+    }
+}
@@ -0,0 +1,133 @@
+use super::{
+    generate_voronoi_spawn_regions, keep_largest_region, most_distant_tile, MapBuilder,
+    SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+
+const SMOOTHING_PASSES: i32 = 15;
+
+/// Cave generator: starts from random noise and smooths it with cellular
+/// automata passes, keeping only the largest connected cave
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        // random noise, roughly 55% floor
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                if rng.roll_dice(1, 100) > 55 {
+                    self.map.tiles[idx] = TileType::Floor;
+                } else {
+                    self.map.tiles[idx] = TileType::Wall;
+                }
+            }
+        }
+
+        self.take_snapshot();
+
+        for _i in 0..SMOOTHING_PASSES {
+            self.smooth();
+            self.take_snapshot();
+        }
+
+        let cave = keep_largest_region(&mut self.map);
+        self.take_snapshot();
+
+        // start on the cave tile closest to the middle of the map
+        let center = rltk::Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = *cave
+            .iter()
+            .min_by_key(|idx| {
+                let x = **idx as i32 % self.map.width;
+                let y = **idx as i32 / self.map.width;
+                (x - center.x).abs() + (y - center.y).abs()
+            })
+            .expect("Cave generation produced no floor");
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let stairs_idx = most_distant_tile(&mut self.map, start_idx);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        let mut noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+        for area in noise_areas.values_mut() {
+            area.retain(|idx| *idx != start_idx);
+            spawner::spawn_region(rng, area, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> CellularAutomataBuilder {
+        return CellularAutomataBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        };
+    }
+
+    /// One cellular automata pass: a tile becomes wall if it is crowded by
+    /// walls or completely isolated, and floor otherwise
+    fn smooth(&mut self) {
+        let mut new_tiles = self.map.tiles.clone();
+
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let mut neighbors = 0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        if (dx != 0 || dy != 0)
+                            && self.map.tiles[self.map.xy_idx(x + dx, y + dy)] == TileType::Wall
+                        {
+                            neighbors += 1;
+                        }
+                    }
+                }
+
+                let idx = self.map.xy_idx(x, y);
+                if neighbors > 4 || neighbors == 0 {
+                    new_tiles[idx] = TileType::Wall;
+                } else {
+                    new_tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+
+        self.map.tiles = new_tiles;
+    }
+}
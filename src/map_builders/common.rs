@@ -0,0 +1,308 @@
+use crate::{Map, Rect, TileType};
+use rltk::{BaseMap, RandomNumberGenerator, SmallVec};
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        dig(map, x, y);
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        dig(map, x, y);
+    }
+}
+
+/// Turns a wall into floor and bridges deep water, never touching the map's
+/// outer border
+fn dig(map: &mut Map, x: i32, y: i32) {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return;
+    }
+    let idx = map.xy_idx(x, y);
+    match map.tiles[idx] {
+        TileType::Wall => map.tiles[idx] = TileType::Floor,
+        TileType::DeepWater => map.tiles[idx] = TileType::Bridge,
+        _ => {}
+    }
+}
+
+/// One door in this many is a secret door
+const SECRET_DOOR_CHANCE: i32 = 8;
+
+/// Hangs a closed door in every one-tile gap where a corridor breaks through
+/// the wall of one of the map's rooms
+pub fn add_doors(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    let rooms = map.rooms.clone();
+    for room in rooms.iter() {
+        for x in room.x1..=room.x2 + 1 {
+            try_place_door(map, rng, x, room.y1);
+            try_place_door(map, rng, x, room.y2 + 1);
+        }
+        for y in room.y1..=room.y2 + 1 {
+            try_place_door(map, rng, room.x1, y);
+            try_place_door(map, rng, room.x2 + 1, y);
+        }
+    }
+}
+
+fn try_place_door(map: &mut Map, rng: &mut RandomNumberGenerator, x: i32, y: i32) {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return;
+    }
+    let idx = map.xy_idx(x, y);
+    if map.tiles[idx] != TileType::Floor {
+        return;
+    }
+
+    let is_wall = |x: i32, y: i32| map.tiles[map.xy_idx(x, y)] == TileType::Wall;
+    let horizontal_gap =
+        is_wall(x - 1, y) && is_wall(x + 1, y) && !is_wall(x, y - 1) && !is_wall(x, y + 1);
+    let vertical_gap =
+        is_wall(x, y - 1) && is_wall(x, y + 1) && !is_wall(x - 1, y) && !is_wall(x + 1, y);
+    // rooms sharing a wall would otherwise get a door on each side of it
+    let is_door = |x: i32, y: i32| {
+        matches!(
+            map.tiles[map.xy_idx(x, y)],
+            TileType::DoorClosed | TileType::SecretDoor
+        )
+    };
+    let next_to_door =
+        is_door(x - 1, y) || is_door(x + 1, y) || is_door(x, y - 1) || is_door(x, y + 1);
+    if (horizontal_gap || vertical_gap) && !next_to_door {
+        if rng.roll_dice(1, SECRET_DOOR_CHANCE) == 1 {
+            map.tiles[idx] = TileType::SecretDoor;
+        } else {
+            map.tiles[idx] = TileType::DoorClosed;
+        }
+    }
+}
+
+/// Flood fills every floor region and turns all but the largest one back
+/// into wall. Returns the tiles of the region that was kept.
+pub fn keep_largest_region(map: &mut Map) -> Vec<usize> {
+    let mut region_of: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut regions: Vec<Vec<usize>> = Vec::new();
+
+    for start in 0..map.tiles.len() {
+        if !map.tiles[start].is_walkable() || region_of[start].is_some() {
+            continue;
+        }
+
+        let region_id = regions.len();
+        let mut region = Vec::new();
+        let mut open_list = vec![start];
+        region_of[start] = Some(region_id);
+
+        while let Some(idx) = open_list.pop() {
+            region.push(idx);
+            let x = idx as i32 % map.width;
+            let y = idx as i32 / map.width;
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter() {
+                if *nx < 0 || *nx >= map.width || *ny < 0 || *ny >= map.height {
+                    continue;
+                }
+                let n_idx = map.xy_idx(*nx, *ny);
+                if map.tiles[n_idx].is_walkable() && region_of[n_idx].is_none() {
+                    region_of[n_idx] = Some(region_id);
+                    open_list.push(n_idx);
+                }
+            }
+        }
+
+        regions.push(region);
+    }
+
+    let largest = regions
+        .iter()
+        .enumerate()
+        .max_by_key(|(_i, region)| region.len())
+        .map(|(i, _region)| i);
+
+    for (idx, region) in region_of.iter().enumerate() {
+        if region.is_some() && *region != largest {
+            map.tiles[idx] = TileType::Wall;
+        }
+    }
+
+    return match largest {
+        Some(i) => regions.swap_remove(i),
+        None => Vec::new(),
+    };
+}
+
+/// The map as the player walks it: one cardinal step at a time, so a gap
+/// that can only be crossed diagonally does not count as a way through
+struct CardinalSteps<'a> {
+    map: &'a Map,
+}
+
+impl BaseMap for CardinalSteps<'_> {
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let width = self.map.width as usize;
+        return self
+            .map
+            .get_available_exits(idx)
+            .into_iter()
+            .filter(|(exit, _cost)| exit.abs_diff(idx) == 1 || exit.abs_diff(idx) == width)
+            .collect();
+    }
+}
+
+/// Floods the map from `start_idx`, giving the walking distance to every tile
+/// along the cardinal steps the player can take. Unreachable tiles are left at
+/// `f32::MAX`.
+pub fn walking_distances(map: &mut Map, start_idx: usize) -> Vec<f32> {
+    map.populate_blocked();
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &[start_idx],
+        &CardinalSteps { map },
+        map.tiles.len() as f32 * 2.0,
+    );
+    return dijkstra_map.map;
+}
+
+/// Returns the reachable tile with the longest walking distance from `start_idx`
+pub fn most_distant_tile(map: &mut Map, start_idx: usize) -> usize {
+    let distances = walking_distances(map, start_idx);
+
+    let mut exit_tile = (start_idx, 0.0f32);
+    for (idx, distance) in distances.iter().enumerate() {
+        if map.tiles[idx].is_walkable() && *distance != f32::MAX && *distance > exit_tile.1 {
+            exit_tile = (idx, *distance);
+        }
+    }
+
+    return exit_tile.0;
+}
+
+/// Splits the floor of a map without rooms into Voronoi cells, giving the
+/// spawner arbitrarily shaped areas to populate. Areas are ordered so that
+/// the same seed always spawns the same things.
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+) -> BTreeMap<i32, Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(rltk::NoiseType::Cellular);
+    noise.set_frequency(0.08);
+    noise.set_cellular_distance_function(rltk::CellularDistanceFunction::Manhattan);
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                let cell_value = (noise.get_noise(x as f32, y as f32) * 10240.0) as i32;
+                noise_areas.entry(cell_value).or_default().push(idx);
+            }
+        }
+    }
+
+    return noise_areas;
+}
+
+/// Walls off every floor tile that cannot be walked to from `start_idx`
+pub fn remove_unreachable_areas(map: &mut Map, start_idx: usize) {
+    let distances = walking_distances(map, start_idx);
+
+    for (idx, distance) in distances.iter().enumerate() {
+        if map.tiles[idx].is_walkable() && *distance == f32::MAX {
+            map.tiles[idx] = TileType::Wall;
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum Symmetry {
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// Digs floor at (x, y) with the given brush, mirrored according to `mode`
+pub fn paint(map: &mut Map, mode: Symmetry, brush_size: i32, x: i32, y: i32) {
+    let center_x = map.width / 2;
+    let center_y = map.height / 2;
+    let dist_x = i32::abs(center_x - x);
+    let dist_y = i32::abs(center_y - y);
+
+    match mode {
+        Symmetry::None => apply_paint(map, brush_size, x, y),
+        Symmetry::Horizontal => {
+            apply_paint(map, brush_size, center_x + dist_x, y);
+            apply_paint(map, brush_size, center_x - dist_x, y);
+        }
+        Symmetry::Vertical => {
+            apply_paint(map, brush_size, x, center_y + dist_y);
+            apply_paint(map, brush_size, x, center_y - dist_y);
+        }
+        Symmetry::Both => {
+            apply_paint(map, brush_size, center_x + dist_x, center_y + dist_y);
+            apply_paint(map, brush_size, center_x - dist_x, center_y + dist_y);
+            apply_paint(map, brush_size, center_x + dist_x, center_y - dist_y);
+            apply_paint(map, brush_size, center_x - dist_x, center_y - dist_y);
+        }
+    }
+}
+
+fn apply_paint(map: &mut Map, brush_size: i32, x: i32, y: i32) {
+    let half_brush = brush_size / 2;
+    for brush_y in y - half_brush..y - half_brush + brush_size {
+        for brush_x in x - half_brush..x - half_brush + brush_size {
+            if brush_x > 0 && brush_x < map.width - 1 && brush_y > 0 && brush_y < map.height - 1 {
+                let idx = map.xy_idx(brush_x, brush_y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+}
+
+/// Moves a digger one step in a random cardinal direction, staying clear of
+/// the map edges
+pub fn stagger(map: &Map, rng: &mut RandomNumberGenerator, x: &mut i32, y: &mut i32) {
+    match rng.roll_dice(1, 4) {
+        1 => {
+            if *x > 2 {
+                *x -= 1;
+            }
+        }
+        2 => {
+            if *x < map.width - 2 {
+                *x += 1;
+            }
+        }
+        3 => {
+            if *y > 2 {
+                *y -= 1;
+            }
+        }
+        _ => {
+            if *y < map.height - 2 {
+                *y += 1;
+            }
+        }
+    }
+}
+
+pub fn floor_tile_count(map: &Map) -> usize {
+    return map
+        .tiles
+        .iter()
+        .filter(|tile| **tile == TileType::Floor)
+        .count();
+}
@@ -0,0 +1,383 @@
+use super::{
+    apply_horizontal_tunnel, apply_vertical_tunnel, remove_unreachable_areas, walking_distances,
+    MapBuilder, SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+
+/// Unreachable areas smaller than this are walled off; larger ones are
+/// worth keeping and get a tunnel back to the rest of the level
+const MIN_REGION_SIZE: usize = 16;
+/// Upper bound on regions handled, so a map that never settles cannot hang
+/// level generation
+const MAX_CONNECT_PASSES: usize = 200;
+/// Stairs closer to the start than this are moved further away
+const MIN_STAIRS_DISTANCE: f32 = 20.0;
+
+/// Post-processing pass run on every level: makes sure every floor tile can be
+/// walked to from the start and that the stairs down sit a reasonable walk away
+pub struct ConnectivityBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for ConnectivityBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.map = self.previous_builder.get_map();
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.spawn_list = self.previous_builder.get_spawn_list().clone();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        if !self.map.tiles[start_idx].is_walkable() {
+            self.map.tiles[start_idx] = TileType::Floor;
+        }
+        // below the town, the player arrives on the way back up
+        if self.map.depth > 0 {
+            self.map.tiles[start_idx] = TileType::StairsUp;
+        }
+
+        self.connect_regions(start_idx);
+        self.place_stairs(rng, start_idx);
+
+        // nothing should spawn inside a wall, on the stairs or on the player
+        let map = &self.map;
+        self.spawn_list
+            .retain(|spawn| map.tiles[spawn.0] == TileType::Floor && spawn.0 != start_idx);
+        self.take_snapshot();
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl ConnectivityBuilder {
+    pub fn new(previous_builder: Box<dyn MapBuilder>) -> ConnectivityBuilder {
+        return ConnectivityBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            previous_builder,
+        };
+    }
+
+    /// Tunnels every sizeable unreachable region back into the level and walls
+    /// off the rest, until everything left can be walked to from the start
+    fn connect_regions(&mut self, start_idx: usize) {
+        for _pass in 0..MAX_CONNECT_PASSES {
+            let distances = walking_distances(&mut self.map, start_idx);
+            let region = match self.first_unreachable_region(&distances) {
+                None => return,
+                Some(region) => region,
+            };
+
+            let mut joined = false;
+            if region.len() >= MIN_REGION_SIZE {
+                self.tunnel_to_reachable(&region, &distances);
+                joined = walking_distances(&mut self.map, start_idx)[region[0]] != f32::MAX;
+            }
+            // a closet, or a region the tunnel could not reach, is walled off
+            if !joined {
+                for idx in region.iter() {
+                    self.map.tiles[*idx] = TileType::Wall;
+                }
+            }
+            self.take_snapshot();
+        }
+
+        // give up on whatever is still cut off
+        remove_unreachable_areas(&mut self.map, start_idx);
+        self.take_snapshot();
+    }
+
+    /// Flood fills (cardinally) from the first unreachable open tile
+    fn first_unreachable_region(&self, distances: &[f32]) -> Option<Vec<usize>> {
+        let is_unreachable =
+            |idx: usize| self.map.tiles[idx].is_walkable() && distances[idx] == f32::MAX;
+
+        let first = (0..self.map.tiles.len()).find(|idx| is_unreachable(*idx))?;
+        let mut in_region = vec![false; self.map.tiles.len()];
+        let mut region = Vec::new();
+        let mut open_list = vec![first];
+        in_region[first] = true;
+
+        while let Some(idx) = open_list.pop() {
+            region.push(idx);
+            let x = idx as i32 % self.map.width;
+            let y = idx as i32 / self.map.width;
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter() {
+                if *nx < 0 || *nx >= self.map.width || *ny < 0 || *ny >= self.map.height {
+                    continue;
+                }
+                let n_idx = self.map.xy_idx(*nx, *ny);
+                if !in_region[n_idx] && is_unreachable(n_idx) {
+                    in_region[n_idx] = true;
+                    open_list.push(n_idx);
+                }
+            }
+        }
+
+        return Some(region);
+    }
+
+    /// Digs an L-shaped corridor from the region to the closest reachable tile
+    fn tunnel_to_reachable(&mut self, region: &[usize], distances: &[f32]) {
+        let width = self.map.width;
+        let reachable: Vec<usize> = (0..self.map.tiles.len())
+            .filter(|idx| self.map.tiles[*idx].is_walkable() && distances[*idx] != f32::MAX)
+            .collect();
+
+        let mut best: Option<(i32, usize, usize)> = None;
+        for from in region.iter() {
+            for to in reachable.iter() {
+                let distance = i32::abs(*from as i32 % width - *to as i32 % width)
+                    + i32::abs(*from as i32 / width - *to as i32 / width);
+                if best.is_none() || distance < best.unwrap().0 {
+                    best = Some((distance, *from, *to));
+                }
+            }
+        }
+
+        if let Some((_distance, from, to)) = best {
+            let (from_x, from_y) = (from as i32 % width, from as i32 / width);
+            let (to_x, to_y) = (to as i32 % width, to as i32 / width);
+            apply_horizontal_tunnel(&mut self.map, from_x, to_x, from_y);
+            apply_vertical_tunnel(&mut self.map, from_y, to_y, to_x);
+        }
+    }
+
+    /// Keeps the builder's stairs if they are reachable and far enough from the
+    /// start, otherwise picks a new spot in the far half of the level
+    fn place_stairs(&mut self, rng: &mut RandomNumberGenerator, start_idx: usize) {
+        let distances = walking_distances(&mut self.map, start_idx);
+        let max_distance = distances
+            .iter()
+            .enumerate()
+            .filter(|(idx, distance)| self.map.tiles[*idx].is_walkable() && **distance != f32::MAX)
+            .map(|(_idx, distance)| *distance)
+            .fold(0.0, f32::max);
+        let wanted_distance = f32::min(MIN_STAIRS_DISTANCE, max_distance / 2.0);
+
+        let mut has_good_stairs = false;
+        for (idx, distance) in distances.iter().enumerate() {
+            if self.map.tiles[idx] == TileType::StairsDown {
+                if *distance >= wanted_distance && *distance != f32::MAX {
+                    has_good_stairs = true;
+                } else {
+                    self.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        if has_good_stairs {
+            return;
+        }
+
+        let candidates: Vec<usize> = (0..self.map.tiles.len())
+            .filter(|idx| {
+                self.map.tiles[*idx] == TileType::Floor
+                    && distances[*idx] != f32::MAX
+                    && distances[*idx] >= max_distance / 2.0
+                    && *idx != start_idx
+            })
+            .collect();
+        if let Some(stairs_idx) = rng.random_slice_entry(&candidates) {
+            self.map.tiles[*stairs_idx] = TileType::StairsDown;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::random_builder;
+    use crate::RunSeed;
+
+    const SEEDS: u64 = 60;
+
+    /// Which tiles the player can walk to from `start`, stepping only
+    /// north, south, east or west as the movement keys do
+    fn reachable_on_foot(map: &Map, start: &Position) -> Vec<bool> {
+        let mut reached = vec![false; map.tiles.len()];
+        let mut open_list = vec![(start.x, start.y)];
+        reached[map.xy_idx(start.x, start.y)] = true;
+        while let Some((x, y)) = open_list.pop() {
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx < 0 || nx >= map.width || ny < 0 || ny >= map.height {
+                    continue;
+                }
+                let idx = map.xy_idx(nx, ny);
+                if !reached[idx] && map.tiles[idx].is_walkable() {
+                    reached[idx] = true;
+                    open_list.push((nx, ny));
+                }
+            }
+        }
+        return reached;
+    }
+
+    fn assert_connected(map: &Map, start: &Position, context: &str) {
+        let start_idx = map.xy_idx(start.x, start.y);
+        assert_ne!(
+            map.tiles[start_idx],
+            TileType::Wall,
+            "{}: start in wall",
+            context
+        );
+
+        let reached = reachable_on_foot(map, start);
+        let mut stairs = 0;
+        for (idx, tile) in map.tiles.iter().enumerate() {
+            if !tile.is_walkable() {
+                continue;
+            }
+            assert!(
+                reached[idx],
+                "{}: tile ({}, {}) is unreachable",
+                context,
+                idx as i32 % map.width,
+                idx as i32 / map.width
+            );
+            if *tile == TileType::StairsDown {
+                stairs += 1;
+            }
+        }
+        assert!(stairs > 0, "{}: no reachable stairs down", context);
+        if map.depth > 0 {
+            assert_eq!(
+                map.tiles[start_idx],
+                TileType::StairsUp,
+                "{}: no stairs up at the start",
+                context
+            );
+        }
+    }
+
+    #[test]
+    fn every_generated_level_is_fully_connected() {
+        for seed in 0..SEEDS {
+            for depth in 0..=4 {
+                let run_seed = RunSeed { value: seed };
+                let mut rng = run_seed.level_rng(depth);
+                let mut builder = random_builder(depth, &mut rng);
+                builder.build_map(&mut rng);
+
+                let map = builder.get_map();
+                let start = builder.get_starting_position();
+                let context = format!("seed {} depth {}", seed, depth);
+                assert_connected(&map, &start, &context);
+
+                for (idx, name) in builder.get_spawn_list().iter() {
+                    assert_eq!(
+                        map.tiles[*idx],
+                        TileType::Floor,
+                        "{}: {} spawned off the floor",
+                        context,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    /// Hands a fixed, deliberately broken map to the connectivity pass
+    struct FixedBuilder {
+        map: Map,
+        spawn_list: Vec<(usize, String)>,
+    }
+
+    impl MapBuilder for FixedBuilder {
+        fn build_map(&mut self, _rng: &mut RandomNumberGenerator) {}
+        fn get_map(&self) -> Map {
+            return self.map.clone();
+        }
+        fn get_starting_position(&self) -> Position {
+            return Position { x: 5, y: 5 };
+        }
+        fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+            return &self.spawn_list;
+        }
+        fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+            return Vec::new();
+        }
+        fn take_snapshot(&mut self) {}
+    }
+
+    fn carve(map: &mut Map, x1: i32, y1: i32, x2: i32, y2: i32) {
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+
+    #[test]
+    fn isolated_rooms_are_reconnected_or_removed() {
+        let mut map = Map::new(1, 80, 43);
+        // the start room, a big isolated room holding the stairs and a closet
+        carve(&mut map, 2, 2, 10, 10);
+        carve(&mut map, 50, 20, 60, 30);
+        carve(&mut map, 30, 38, 31, 39);
+        let stairs_idx = map.xy_idx(55, 25);
+        map.tiles[stairs_idx] = TileType::StairsDown;
+        let closet_idx = map.xy_idx(30, 38);
+        let spawn_list = vec![(closet_idx, "Goblin".to_string())];
+
+        let mut builder = ConnectivityBuilder::new(Box::new(FixedBuilder { map, spawn_list }));
+        let mut rng = RandomNumberGenerator::seeded(1);
+        builder.build_map(&mut rng);
+        let map = builder.get_map();
+
+        assert_connected(&map, &Position { x: 5, y: 5 }, "handmade map");
+        assert_eq!(map.tiles[stairs_idx], TileType::StairsDown);
+        assert_eq!(map.tiles[closet_idx], TileType::Wall);
+        assert!(builder.get_spawn_list().is_empty());
+    }
+
+    #[test]
+    fn regions_that_cannot_be_tunnelled_to_are_walled_off() {
+        let mut map = Map::new(1, 80, 43);
+        carve(&mut map, 2, 2, 10, 10);
+        carve(&mut map, 60, 20, 70, 30);
+        // a strip along the top edge, where no tunnel is allowed to dig
+        carve(&mut map, 40, 0, 60, 0);
+        let strip_idx = map.xy_idx(50, 0);
+
+        let mut builder = ConnectivityBuilder::new(Box::new(FixedBuilder {
+            map,
+            spawn_list: Vec::new(),
+        }));
+        let mut rng = RandomNumberGenerator::seeded(1);
+        builder.build_map(&mut rng);
+        let map = builder.get_map();
+
+        assert_connected(&map, &Position { x: 5, y: 5 }, "strip on the edge");
+        assert_eq!(map.tiles[strip_idx], TileType::Wall);
+    }
+}
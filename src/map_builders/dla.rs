@@ -0,0 +1,249 @@
+use super::{
+    floor_tile_count, generate_voronoi_spawn_regions, most_distant_tile, paint,
+    remove_unreachable_areas, stagger, MapBuilder, Symmetry, SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::{Point, RandomNumberGenerator};
+
+const PARTICLES_PER_SNAPSHOT: i32 = 10;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DLAAlgorithm {
+    /// Diggers start anywhere and wander until they touch the existing floor
+    WalkInwards,
+    /// Diggers start in the centre and wander until they reach solid rock
+    WalkOutwards,
+    /// Diggers start anywhere and head straight for the centre
+    CentralAttractor,
+}
+
+/// Diffusion-limited aggregation generator: grows a tunnel network outwards
+/// from the centre of the map one particle at a time
+pub struct DLABuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    algorithm: DLAAlgorithm,
+    brush_size: i32,
+    symmetry: Symmetry,
+    floor_percent: f32,
+}
+
+impl MapBuilder for DLABuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        // seed the aggregate with a small cross in the middle of the map
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let width = self.map.width as usize;
+        for idx in [
+            start_idx,
+            start_idx - 1,
+            start_idx + 1,
+            start_idx - width,
+            start_idx + width,
+        ]
+        .iter()
+        {
+            self.map.tiles[*idx] = TileType::Floor;
+        }
+
+        let desired_floor_tiles = (self.floor_percent * self.map.tiles.len() as f32) as usize;
+        let mut particle_count = 0;
+        while floor_tile_count(&self.map) < desired_floor_tiles {
+            let (paint_x, paint_y) = match self.algorithm {
+                DLAAlgorithm::WalkInwards => self.particle_walk_inwards(rng),
+                DLAAlgorithm::WalkOutwards => self.particle_walk_outwards(rng),
+                DLAAlgorithm::CentralAttractor => self.particle_central_attractor(rng),
+            };
+            paint(
+                &mut self.map,
+                self.symmetry,
+                self.brush_size,
+                paint_x,
+                paint_y,
+            );
+
+            // single particles are too small to watch one at a time
+            particle_count += 1;
+            if particle_count % PARTICLES_PER_SNAPSHOT == 0 {
+                self.take_snapshot();
+            }
+        }
+
+        remove_unreachable_areas(&mut self.map, start_idx);
+        let stairs_idx = most_distant_tile(&mut self.map, start_idx);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        let mut noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+        for area in noise_areas.values_mut() {
+            area.retain(|idx| *idx != start_idx);
+            spawner::spawn_region(rng, area, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl DLABuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        algorithm: DLAAlgorithm,
+        brush_size: i32,
+        symmetry: Symmetry,
+        floor_percent: f32,
+    ) -> DLABuilder {
+        return DLABuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            algorithm,
+            brush_size,
+            symmetry,
+            floor_percent,
+        };
+    }
+
+    pub fn walk_inwards(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        return DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkInwards,
+            1,
+            Symmetry::None,
+            0.25,
+        );
+    }
+
+    pub fn walk_outwards(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        return DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkOutwards,
+            2,
+            Symmetry::None,
+            0.25,
+        );
+    }
+
+    pub fn central_attractor(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        return DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::CentralAttractor,
+            2,
+            Symmetry::None,
+            0.25,
+        );
+    }
+
+    pub fn insectoid(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        return DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::CentralAttractor,
+            2,
+            Symmetry::Horizontal,
+            0.25,
+        );
+    }
+
+    pub fn rorschach(new_depth: i32, width: i32, height: i32) -> DLABuilder {
+        return DLABuilder::new(
+            new_depth,
+            width,
+            height,
+            DLAAlgorithm::WalkInwards,
+            1,
+            Symmetry::Vertical,
+            0.25,
+        );
+    }
+
+    fn random_point(&self, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        return (
+            rng.roll_dice(1, self.map.width - 3) + 1,
+            rng.roll_dice(1, self.map.height - 3) + 1,
+        );
+    }
+
+    /// Returns the last wall tile a random walker stood on before stepping
+    /// onto the aggregate
+    fn particle_walk_inwards(&self, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        let (mut digger_x, mut digger_y) = self.random_point(rng);
+        let (mut prev_x, mut prev_y) = (digger_x, digger_y);
+        while self.map.tiles[self.map.xy_idx(digger_x, digger_y)] == TileType::Wall {
+            prev_x = digger_x;
+            prev_y = digger_y;
+            stagger(&self.map, rng, &mut digger_x, &mut digger_y);
+        }
+        return (prev_x, prev_y);
+    }
+
+    /// Returns the first wall tile a walker starting in the centre runs into
+    fn particle_walk_outwards(&self, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        let (mut digger_x, mut digger_y) = (self.starting_position.x, self.starting_position.y);
+        while self.map.tiles[self.map.xy_idx(digger_x, digger_y)] != TileType::Wall {
+            stagger(&self.map, rng, &mut digger_x, &mut digger_y);
+        }
+        return (digger_x, digger_y);
+    }
+
+    /// Like `particle_walk_inwards`, but the particle travels in a straight line
+    /// towards the centre instead of wandering
+    fn particle_central_attractor(&self, rng: &mut RandomNumberGenerator) -> (i32, i32) {
+        let (mut digger_x, mut digger_y) = self.random_point(rng);
+        let (mut prev_x, mut prev_y) = (digger_x, digger_y);
+        let path = rltk::line2d(
+            rltk::LineAlg::Bresenham,
+            Point::new(digger_x, digger_y),
+            Point::new(self.starting_position.x, self.starting_position.y),
+        );
+
+        for step in path.iter() {
+            if self.map.tiles[self.map.xy_idx(digger_x, digger_y)] != TileType::Wall {
+                break;
+            }
+            prev_x = digger_x;
+            prev_y = digger_y;
+            digger_x = step.x;
+            digger_y = step.y;
+        }
+        return (prev_x, prev_y);
+    }
+}
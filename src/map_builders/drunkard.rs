@@ -0,0 +1,202 @@
+use super::{
+    floor_tile_count, generate_voronoi_spawn_regions, most_distant_tile, paint,
+    remove_unreachable_areas, stagger, MapBuilder, Symmetry, SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum DrunkSpawnMode {
+    StartingPoint,
+    Random,
+}
+
+pub struct DrunkardSettings {
+    pub spawn_mode: DrunkSpawnMode,
+    /// How many steps each digger takes before giving up
+    pub drunken_lifetime: i32,
+    /// Fraction of the map that must be floor before digging stops
+    pub floor_percent: f32,
+    pub brush_size: i32,
+    pub symmetry: Symmetry,
+}
+
+/// Drunkard's walk generator: sends out randomly staggering diggers until
+/// enough of the map has been dug out
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    settings: DrunkardSettings,
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        let desired_floor_tiles =
+            (self.settings.floor_percent * self.map.tiles.len() as f32) as usize;
+        let mut digger_count = 0;
+        while floor_tile_count(&self.map) < desired_floor_tiles {
+            let (mut drunk_x, mut drunk_y) = match self.settings.spawn_mode {
+                DrunkSpawnMode::Random if digger_count > 0 => (
+                    rng.roll_dice(1, self.map.width - 3) + 1,
+                    rng.roll_dice(1, self.map.height - 3) + 1,
+                ),
+                _ => (self.starting_position.x, self.starting_position.y),
+            };
+
+            for _step in 0..self.settings.drunken_lifetime {
+                paint(
+                    &mut self.map,
+                    self.settings.symmetry,
+                    self.settings.brush_size,
+                    drunk_x,
+                    drunk_y,
+                );
+                stagger(&self.map, rng, &mut drunk_x, &mut drunk_y);
+            }
+
+            digger_count += 1;
+            self.take_snapshot();
+        }
+
+        remove_unreachable_areas(&mut self.map, start_idx);
+        let stairs_idx = most_distant_tile(&mut self.map, start_idx);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        let mut noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+        for area in noise_areas.values_mut() {
+            area.retain(|idx| *idx != start_idx);
+            spawner::spawn_region(rng, area, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        settings: DrunkardSettings,
+    ) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            settings,
+        };
+    }
+
+    /// Long-lived diggers from the centre carve one big open cavern
+    pub fn open_area(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::StartingPoint,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+                brush_size: 1,
+                symmetry: Symmetry::None,
+            },
+        );
+    }
+
+    /// Diggers start anywhere, producing several halls joined by tunnels
+    pub fn open_halls(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+                brush_size: 1,
+                symmetry: Symmetry::None,
+            },
+        );
+    }
+
+    /// Short-lived diggers leave narrow, twisting passages
+    pub fn winding_passages(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+                brush_size: 1,
+                symmetry: Symmetry::None,
+            },
+        );
+    }
+
+    pub fn fat_passages(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+                brush_size: 2,
+                symmetry: Symmetry::None,
+            },
+        );
+    }
+
+    pub fn fearful_symmetry(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        return DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: DrunkSpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+                brush_size: 1,
+                symmetry: Symmetry::Both,
+            },
+        );
+    }
+}
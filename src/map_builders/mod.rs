@@ -0,0 +1,215 @@
+use super::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+mod bsp_dungeon;
+use bsp_dungeon::BspDungeonBuilder;
+mod cellular_automata;
+use cellular_automata::CellularAutomataBuilder;
+mod connectivity;
+use connectivity::ConnectivityBuilder;
+mod dla;
+use dla::DLABuilder;
+mod drunkard;
+use drunkard::DrunkardsWalkBuilder;
+mod prefab_builder;
+use prefab_builder::{prefab_levels, PrefabBuilder};
+mod common;
+use common::*;
+mod simple_map;
+use simple_map::SimpleMapBuilder;
+mod terrain_features;
+use terrain_features::TerrainFeatureBuilder;
+mod town;
+use town::TownBuilder;
+mod wave_function_collapse;
+use wave_function_collapse::WaveFunctionCollapseBuilder;
+
+/// Plays back every step of map generation before a level starts.
+/// Flip this on when tuning a builder.
+pub const SHOW_MAPGEN_VISUALIZER: bool = false;
+
+/// A map generation algorithm.
+/// Builds the map, decides where the player starts and populates the level.
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn get_spawn_list(&self) -> &Vec<(usize, String)>;
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>>;
+    /// Records the current tiles for the visualizer
+    fn take_snapshot(&mut self);
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        for entity in self.get_spawn_list().iter() {
+            spawner::spawn_entity(ecs, &(&entity.0, &entity.1));
+        }
+    }
+}
+
+/// Every generator `random_builder` picks from, by the name the `mapgen`
+/// tool knows it by. The town only ever appears at depth 0.
+pub const BUILDER_NAMES: [&str; 17] = [
+    "town",
+    "simple",
+    "bsp",
+    "cellular",
+    "open_area",
+    "open_halls",
+    "winding_passages",
+    "fat_passages",
+    "fearful_symmetry",
+    "walk_inwards",
+    "walk_outwards",
+    "central_attractor",
+    "insectoid",
+    "rorschach",
+    "wfc_catacombs",
+    "wfc_cellular",
+    "catacombs",
+];
+
+/// Picks the map builder to use for the given depth
+pub fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    // every run starts in town
+    if new_depth == 0 {
+        return named_builder("town", new_depth, rng).unwrap();
+    }
+
+    let roll = rng.roll_dice(1, BUILDER_NAMES.len() as i32 - 1);
+    return named_builder(BUILDER_NAMES[roll as usize], new_depth, rng).unwrap();
+}
+
+/// The named generator, wrapped in the same vault, terrain and connectivity
+/// passes a random level gets. Returns None for an unknown name.
+pub fn named_builder(
+    name: &str,
+    new_depth: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Option<Box<dyn MapBuilder>> {
+    if name == "town" {
+        return Some(Box::new(ConnectivityBuilder::new(Box::new(
+            TownBuilder::new(),
+        ))));
+    }
+
+    // levels come in different sizes; the camera scrolls over the bigger ones
+    let width = 64 + rng.roll_dice(1, 8) * 8;
+    let height = 40 + rng.roll_dice(1, 5) * 8;
+
+    let mut builder: Box<dyn MapBuilder> = match name {
+        "simple" => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+        "bsp" => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        "cellular" => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        "open_area" => Box::new(DrunkardsWalkBuilder::open_area(new_depth, width, height)),
+        "open_halls" => Box::new(DrunkardsWalkBuilder::open_halls(new_depth, width, height)),
+        "winding_passages" => Box::new(DrunkardsWalkBuilder::winding_passages(
+            new_depth, width, height,
+        )),
+        "fat_passages" => Box::new(DrunkardsWalkBuilder::fat_passages(new_depth, width, height)),
+        "fearful_symmetry" => Box::new(DrunkardsWalkBuilder::fearful_symmetry(
+            new_depth, width, height,
+        )),
+        "walk_inwards" => Box::new(DLABuilder::walk_inwards(new_depth, width, height)),
+        "walk_outwards" => Box::new(DLABuilder::walk_outwards(new_depth, width, height)),
+        "central_attractor" => Box::new(DLABuilder::central_attractor(new_depth, width, height)),
+        "insectoid" => Box::new(DLABuilder::insectoid(new_depth, width, height)),
+        "rorschach" => Box::new(DLABuilder::rorschach(new_depth, width, height)),
+        "wfc_catacombs" => {
+            let sample = PrefabBuilder::rex_level(new_depth, prefab_levels::CATACOMBS);
+            Box::new(WaveFunctionCollapseBuilder::new(
+                new_depth,
+                width,
+                height,
+                Box::new(sample),
+            ))
+        }
+        "wfc_cellular" => {
+            let sample = CellularAutomataBuilder::new(new_depth, width, height);
+            Box::new(WaveFunctionCollapseBuilder::new(
+                new_depth,
+                width,
+                height,
+                Box::new(sample),
+            ))
+        }
+        "catacombs" => {
+            let prefab = PrefabBuilder::rex_level(new_depth, prefab_levels::CATACOMBS);
+            return Some(Box::new(ConnectivityBuilder::new(Box::new(prefab))));
+        }
+        _ => return None,
+    };
+
+    if rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(PrefabBuilder::room_vaults(new_depth, builder));
+    }
+    if rng.roll_dice(1, 3) == 1 {
+        builder = Box::new(TerrainFeatureBuilder::new(new_depth, builder));
+    }
+
+    // whatever was generated, make sure all of it can be reached
+    return Some(Box::new(ConnectivityBuilder::new(builder)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RunSeed;
+
+    /// Builds a handful of levels with every named generator
+    fn sample_levels() -> Vec<(String, Map)> {
+        let mut levels = Vec::new();
+        for name in BUILDER_NAMES.iter() {
+            for seed in 0..3 {
+                for depth in [1, 4].iter() {
+                    let mut rng = RunSeed { value: seed }.level_rng(*depth);
+                    let mut builder = named_builder(name, *depth, &mut rng).unwrap();
+                    builder.build_map(&mut rng);
+                    levels.push((
+                        format!("{} seed {} depth {}", name, seed, depth),
+                        builder.get_map(),
+                    ));
+                }
+            }
+        }
+        return levels;
+    }
+
+    #[test]
+    fn unknown_builder_names_are_rejected() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert!(named_builder("maze", 1, &mut rng).is_none());
+    }
+
+    #[test]
+    fn every_level_has_stairs_down() {
+        for (label, map) in sample_levels() {
+            assert!(
+                map.tiles.contains(&TileType::StairsDown),
+                "{} has no stairs down",
+                label
+            );
+        }
+    }
+
+    #[test]
+    fn no_walkable_tile_on_the_border() {
+        for (label, map) in sample_levels() {
+            for x in 0..map.width {
+                for y in 0..map.height {
+                    if x > 0 && x < map.width - 1 && y > 0 && y < map.height - 1 {
+                        continue;
+                    }
+                    let tile = map.tiles[map.xy_idx(x, y)];
+                    assert!(
+                        !tile.is_walkable(),
+                        "{} has {:?} on the border at {},{}",
+                        label,
+                        tile,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
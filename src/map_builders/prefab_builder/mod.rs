@@ -0,0 +1,253 @@
+use super::{most_distant_tile, MapBuilder, SHOW_MAPGEN_VISUALIZER};
+use crate::{Map, Position, TileType};
+use rltk::rex::{XpCell, XpFile};
+use rltk::RandomNumberGenerator;
+use std::collections::HashSet;
+use std::fs::File;
+pub mod prefab_levels;
+use prefab_levels::PrefabLevel;
+mod prefab_vaults;
+use prefab_vaults::{PrefabVault, VAULTS};
+
+pub enum PrefabMode {
+    /// Loads a whole level from a REX Paint file
+    RexLevel { level: PrefabLevel },
+    /// Builds a level with another builder, then stamps vaults into it
+    RoomVaults,
+}
+
+/// Builds levels from REX Paint (.xp) files.
+/// The first layer holds the terrain, later layers are drawn on top of it and
+/// their empty cells are ignored, so spawn markers can live on their own layer.
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    mode: PrefabMode,
+    previous_builder: Option<Box<dyn MapBuilder>>,
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        match self.mode {
+            PrefabMode::RexLevel { level } => self.load_rex_level(level),
+            PrefabMode::RoomVaults => self.apply_room_vaults(rng),
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl PrefabBuilder {
+    pub fn rex_level(new_depth: i32, level: PrefabLevel) -> PrefabBuilder {
+        return PrefabBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            mode: PrefabMode::RexLevel { level },
+            previous_builder: None,
+        };
+    }
+
+    pub fn room_vaults(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> PrefabBuilder {
+        return PrefabBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            mode: PrefabMode::RoomVaults,
+            previous_builder: Some(previous_builder),
+        };
+    }
+
+    fn load_rex_level(&mut self, level: PrefabLevel) {
+        // the level is exactly as big as the template
+        let xp_file = load_xp(level.template);
+        self.map = Map::new(
+            self.depth,
+            xp_file.layers[0].width as i32,
+            xp_file.layers[0].height as i32,
+        );
+        self.apply_xp(&xp_file, 0, 0);
+        self.take_snapshot();
+
+        // levels without a marked exit get one as far from the start as possible
+        if !self.map.tiles.contains(&TileType::StairsDown) {
+            let start_idx = self
+                .map
+                .xy_idx(self.starting_position.x, self.starting_position.y);
+            let stairs_idx = most_distant_tile(&mut self.map, start_idx);
+            self.map.tiles[stairs_idx] = TileType::StairsDown;
+            self.take_snapshot();
+        }
+    }
+
+    fn apply_room_vaults(&mut self, rng: &mut RandomNumberGenerator) {
+        if let Some(previous_builder) = self.previous_builder.as_mut() {
+            previous_builder.build_map(rng);
+            self.map = previous_builder.get_map();
+            self.starting_position = previous_builder.get_starting_position();
+            self.spawn_list = previous_builder.get_spawn_list().clone();
+            self.history = previous_builder.get_snapshot_history();
+        }
+
+        let possible_vaults: Vec<&PrefabVault> = VAULTS
+            .iter()
+            .filter(|vault| self.depth >= vault.first_depth && self.depth <= vault.last_depth)
+            .collect();
+        if possible_vaults.is_empty() {
+            return;
+        }
+
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let mut used_tiles: HashSet<usize> = HashSet::new();
+
+        let n_vaults = rng.roll_dice(1, 3);
+        for _i in 0..n_vaults {
+            let vault_index = (rng.roll_dice(1, possible_vaults.len() as i32) - 1) as usize;
+            let xp_file = load_xp(possible_vaults[vault_index].template);
+            let vault_width = xp_file.layers[0].width as i32;
+            let vault_height = xp_file.layers[0].height as i32;
+
+            // a vault fits anywhere its whole footprint is open floor
+            let mut vault_positions: Vec<Position> = Vec::new();
+            for y in 1..self.map.height - vault_height {
+                for x in 1..self.map.width - vault_width {
+                    let mut possible = true;
+                    for idx in footprint(&self.map, x, y, vault_width, vault_height) {
+                        if self.map.tiles[idx] != TileType::Floor
+                            || idx == start_idx
+                            || used_tiles.contains(&idx)
+                        {
+                            possible = false;
+                            break;
+                        }
+                    }
+                    if possible {
+                        vault_positions.push(Position { x, y });
+                    }
+                }
+            }
+
+            if vault_positions.is_empty() {
+                continue;
+            }
+
+            let pos_index = (rng.roll_dice(1, vault_positions.len() as i32) - 1) as usize;
+            let pos = &vault_positions[pos_index];
+            let vault_tiles = footprint(&self.map, pos.x, pos.y, vault_width, vault_height);
+
+            self.spawn_list
+                .retain(|spawn| !vault_tiles.contains(&spawn.0));
+            self.apply_xp(&xp_file, pos.x, pos.y);
+            used_tiles.extend(vault_tiles);
+            self.take_snapshot();
+        }
+    }
+
+    fn apply_xp(&mut self, xp_file: &XpFile, offset_x: i32, offset_y: i32) {
+        for (layer_index, layer) in xp_file.layers.iter().enumerate() {
+            for y in 0..layer.height {
+                for x in 0..layer.width {
+                    let map_x = x as i32 + offset_x;
+                    let map_y = y as i32 + offset_y;
+                    if map_x >= self.map.width || map_y >= self.map.height {
+                        continue;
+                    }
+
+                    let cell = layer.get(x, y).unwrap();
+                    if layer_index > 0 && is_empty_cell(cell) {
+                        continue;
+                    }
+
+                    let idx = self.map.xy_idx(map_x, map_y);
+                    self.char_to_map(cell.ch as u8 as char, idx);
+                }
+            }
+        }
+    }
+
+    fn char_to_map(&mut self, ch: char, idx: usize) {
+        match ch {
+            ' ' | '.' => self.map.tiles[idx] = TileType::Floor,
+            '#' => self.map.tiles[idx] = TileType::Wall,
+            '>' => self.map.tiles[idx] = TileType::StairsDown,
+            '+' => self.map.tiles[idx] = TileType::DoorClosed,
+            '@' => {
+                self.map.tiles[idx] = TileType::Floor;
+                self.starting_position = Position {
+                    x: idx as i32 % self.map.width,
+                    y: idx as i32 / self.map.width,
+                };
+            }
+            _ => match marker_to_spawn(ch) {
+                Some(name) => {
+                    self.map.tiles[idx] = TileType::Floor;
+                    self.spawn_list.push((idx, name.to_string()));
+                }
+                None => {
+                    rltk::console::log(format!("Unknown glyph loading prefab: {}", ch));
+                }
+            },
+        }
+    }
+}
+
+/// Maps a REX Paint glyph to the entity it spawns
+fn marker_to_spawn(ch: char) -> Option<&'static str> {
+    return match ch {
+        'g' => Some("Goblin"),
+        'o' => Some("Orc"),
+        '!' => Some("Health Potion"),
+        ')' => Some("Scroll of Magic Missile"),
+        '/' => Some("Dagger"),
+        '(' => Some("Shield"),
+        _ => None,
+    };
+}
+
+fn is_empty_cell(cell: &XpCell) -> bool {
+    return cell.ch == 0 || cell.ch == 32;
+}
+
+fn footprint(map: &Map, x: i32, y: i32, width: i32, height: i32) -> Vec<usize> {
+    let mut tiles = Vec::new();
+    for ty in y..y + height {
+        for tx in x..x + width {
+            tiles.push(map.xy_idx(tx, ty));
+        }
+    }
+    return tiles;
+}
+
+fn load_xp(path: &str) -> XpFile {
+    let mut file = File::open(path).expect("Unable to open prefab file");
+    return XpFile::read(&mut file).expect("Unable to read prefab file");
+}
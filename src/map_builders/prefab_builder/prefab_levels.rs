@@ -0,0 +1,9 @@
+/// A complete, hand-authored level stored as a REX Paint file
+#[derive(PartialEq, Copy, Clone)]
+pub struct PrefabLevel {
+    pub template: &'static str,
+}
+
+pub const CATACOMBS: PrefabLevel = PrefabLevel {
+    template: "./resources/prefabs/catacombs.xp",
+};
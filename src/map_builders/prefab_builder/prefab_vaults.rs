@@ -0,0 +1,33 @@
+/// A small set piece that can be stamped into an already generated level
+#[derive(PartialEq, Copy, Clone)]
+pub struct PrefabVault {
+    pub template: &'static str,
+    pub first_depth: i32,
+    pub last_depth: i32,
+}
+
+pub const GOBLIN_CAMP: PrefabVault = PrefabVault {
+    template: "./resources/prefabs/vault_goblin_camp.xp",
+    first_depth: 1,
+    last_depth: 4,
+};
+
+pub const SHRINE: PrefabVault = PrefabVault {
+    template: "./resources/prefabs/vault_shrine.xp",
+    first_depth: 1,
+    last_depth: 100,
+};
+
+pub const ORC_GUARDROOM: PrefabVault = PrefabVault {
+    template: "./resources/prefabs/vault_orc_guardroom.xp",
+    first_depth: 3,
+    last_depth: 100,
+};
+
+pub const ARMOURY: PrefabVault = PrefabVault {
+    template: "./resources/prefabs/vault_armoury.xp",
+    first_depth: 5,
+    last_depth: 100,
+};
+
+pub const VAULTS: &[PrefabVault] = &[GOBLIN_CAMP, SHRINE, ORC_GUARDROOM, ARMOURY];
@@ -0,0 +1,172 @@
+use super::{walking_distances, MapBuilder, SHOW_MAPGEN_VISUALIZER};
+use crate::{Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+
+/// Lava only shows up from this depth on
+const LAVA_DEPTH: i32 = 3;
+
+/// Floods a few patches of another builder's level with water or lava. A pool
+/// that would cut part of the level off is drained again.
+pub struct TerrainFeatureBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    previous_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for TerrainFeatureBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.previous_builder.build_map(rng);
+        self.map = self.previous_builder.get_map();
+        self.starting_position = self.previous_builder.get_starting_position();
+        self.spawn_list = self.previous_builder.get_spawn_list().clone();
+        self.history = self.previous_builder.get_snapshot_history();
+
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+
+        let n_pools = rng.roll_dice(1, 3);
+        for _i in 0..n_pools {
+            let floor: Vec<usize> = (0..self.map.tiles.len())
+                .filter(|idx| self.map.tiles[*idx] == TileType::Floor && *idx != start_idx)
+                .collect();
+            let center = match rng.random_slice_entry(&floor) {
+                None => return,
+                Some(idx) => *idx,
+            };
+            let is_lava = self.depth >= LAVA_DEPTH && rng.roll_dice(1, 3) == 1;
+            let radius = rng.range(2, 5) as f32;
+
+            let before = self.map.tiles.clone();
+            let reachable_before = walking_distances(&mut self.map, start_idx);
+            self.flood(center, radius, is_lava, start_idx);
+
+            if self.cuts_anything_off(start_idx, &reachable_before) {
+                self.map.tiles = before;
+            } else {
+                self.take_snapshot();
+            }
+        }
+
+        // nothing spawns in a pool
+        let map = &self.map;
+        self.spawn_list
+            .retain(|spawn| map.tiles[spawn.0] == TileType::Floor);
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl TerrainFeatureBuilder {
+    pub fn new(new_depth: i32, previous_builder: Box<dyn MapBuilder>) -> TerrainFeatureBuilder {
+        return TerrainFeatureBuilder {
+            map: Map::default(),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            previous_builder,
+        };
+    }
+
+    /// Turns the floor around `center` into a pool: lava, or water that is
+    /// deep in the middle and shallow around the rim
+    fn flood(&mut self, center: usize, radius: f32, is_lava: bool, start_idx: usize) {
+        let center = rltk::Point::new(
+            center as i32 % self.map.width,
+            center as i32 / self.map.width,
+        );
+        let reach = radius as i32;
+        for y in center.y - reach..=center.y + reach {
+            for x in center.x - reach..=center.x + reach {
+                if x < 1 || x > self.map.width - 2 || y < 1 || y > self.map.height - 2 {
+                    continue;
+                }
+                let idx = self.map.xy_idx(x, y);
+                if self.map.tiles[idx] != TileType::Floor || idx == start_idx {
+                    continue;
+                }
+
+                let distance =
+                    rltk::DistanceAlg::Pythagoras.distance2d(center, rltk::Point::new(x, y));
+                if distance > radius {
+                    continue;
+                }
+                self.map.tiles[idx] = if is_lava {
+                    TileType::Lava
+                } else if distance < radius - 1.5 {
+                    TileType::DeepWater
+                } else {
+                    TileType::ShallowWater
+                };
+            }
+        }
+    }
+
+    /// Could everything that was reachable before, and is still walkable, still
+    /// be walked to? A pool that leaves only a diagonal gap counts as a wall.
+    fn cuts_anything_off(&mut self, start_idx: usize, reachable_before: &[f32]) -> bool {
+        let reachable_after = walking_distances(&mut self.map, start_idx);
+        for (idx, before) in reachable_before.iter().enumerate() {
+            if *before != f32::MAX
+                && self.map.tiles[idx].is_walkable()
+                && reachable_after[idx] == f32::MAX
+            {
+                return true;
+            }
+        }
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::simple_map::SimpleMapBuilder;
+
+    #[test]
+    fn pools_leaving_only_a_diagonal_gap_cut_the_level_off() {
+        let mut builder = TerrainFeatureBuilder::new(1, Box::new(SimpleMapBuilder::new(1, 30, 10)));
+        builder.map = Map::new(1, 30, 10);
+        // a corridor that turns one step up at x = 10
+        for x in 2..=10 {
+            let idx = builder.map.xy_idx(x, 5);
+            builder.map.tiles[idx] = TileType::Floor;
+        }
+        for x in 10..=20 {
+            let idx = builder.map.xy_idx(x, 4);
+            builder.map.tiles[idx] = TileType::Floor;
+        }
+        let start_idx = builder.map.xy_idx(2, 5);
+        let reachable_before = walking_distances(&mut builder.map, start_idx);
+        assert!(!builder.cuts_anything_off(start_idx, &reachable_before));
+
+        // flooding the corner leaves (10, 5) and (11, 4) touching diagonally
+        let corner_idx = builder.map.xy_idx(10, 4);
+        builder.map.tiles[corner_idx] = TileType::DeepWater;
+        assert!(builder.cuts_anything_off(start_idx, &reachable_before));
+    }
+}
@@ -0,0 +1,159 @@
+use super::{apply_room_to_map, MapBuilder, SHOW_MAPGEN_VISUALIZER};
+use crate::{spawner, Map, Position, Rect, TileType};
+use rltk::RandomNumberGenerator;
+
+const TOWN_WIDTH: i32 = 80;
+const TOWN_HEIGHT: i32 = 43;
+/// The main road runs east to west along these rows
+const ROAD_TOP: i32 = 20;
+const ROAD_BOTTOM: i32 = 22;
+
+/// Houses north of the road, as `Rect::new` arguments for their insides
+const NORTH_HOUSES: [(i32, i32, i32, i32); 4] =
+    [(4, 4, 10, 8), (19, 6, 8, 6), (33, 3, 12, 9), (51, 5, 9, 7)];
+/// Houses south of the road
+const SOUTH_HOUSES: [(i32, i32, i32, i32); 4] = [
+    (6, 27, 9, 7),
+    (21, 26, 11, 9),
+    (38, 28, 8, 6),
+    (53, 26, 10, 8),
+];
+/// The ruined tower at the east end of the road that holds the way down
+const ENTRANCE: (i32, i32, i32, i32) = (69, 17, 7, 7);
+/// A river runs north to south between the houses and the tower, with its
+/// deep channel in this column
+const RIVER_X: i32 = 66;
+
+/// The handmade town the player starts in: a road lined with houses, leading
+/// to the entrance of the dungeon
+pub struct TownBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+}
+
+impl MapBuilder for TownBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        // open ground inside a wall
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Floor;
+            }
+        }
+        self.take_snapshot();
+
+        for (x, y, w, h) in NORTH_HOUSES.iter() {
+            let house = Rect::new(*x, *y, *w, *h);
+            let (door_x, _) = house.center();
+            self.build_house(&house, door_x, house.y2 + 1);
+            self.lay_road(door_x, door_x, house.y2 + 2, ROAD_TOP - 1);
+        }
+        for (x, y, w, h) in SOUTH_HOUSES.iter() {
+            let house = Rect::new(*x, *y, *w, *h);
+            let (door_x, _) = house.center();
+            self.build_house(&house, door_x, house.y1);
+            self.lay_road(door_x, door_x, ROAD_BOTTOM + 1, house.y1 - 1);
+        }
+
+        let (x, y, w, h) = ENTRANCE;
+        let entrance = Rect::new(x, y, w, h);
+        self.build_walls(&entrance);
+        apply_room_to_map(&mut self.map, &entrance);
+        self.lay_road(1, entrance.x1, ROAD_TOP, ROAD_BOTTOM);
+        self.dig_river();
+        let (stairs_x, stairs_y) = entrance.center();
+        let stairs_idx = self.map.xy_idx(stairs_x, stairs_y);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        self.starting_position = Position {
+            x: 2,
+            y: (ROAD_TOP + ROAD_BOTTOM) / 2,
+        };
+
+        for house in self.map.rooms.iter() {
+            spawner::spawn_room(&self.map, rng, house, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl TownBuilder {
+    pub fn new() -> TownBuilder {
+        return TownBuilder {
+            map: Map::new(0, TOWN_WIDTH, TOWN_HEIGHT),
+            starting_position: Position { x: 0, y: 0 },
+            depth: 0,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+        };
+    }
+
+    fn build_house(&mut self, house: &Rect, door_x: i32, door_y: i32) {
+        self.build_walls(house);
+        apply_room_to_map(&mut self.map, house);
+        let door_idx = self.map.xy_idx(door_x, door_y);
+        self.map.tiles[door_idx] = TileType::DoorClosed;
+        self.map.rooms.push(*house);
+        self.take_snapshot();
+    }
+
+    /// Walls in the outline around a room's floor
+    fn build_walls(&mut self, room: &Rect) {
+        for y in room.y1..=room.y2 + 1 {
+            for x in room.x1..=room.x2 + 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Wall;
+            }
+        }
+    }
+
+    /// The road crosses the river on a bridge
+    fn dig_river(&mut self) {
+        for y in 1..self.map.height - 1 {
+            for x in RIVER_X - 1..=RIVER_X + 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = if (ROAD_TOP..=ROAD_BOTTOM).contains(&y) {
+                    TileType::Bridge
+                } else if x == RIVER_X {
+                    TileType::DeepWater
+                } else {
+                    TileType::ShallowWater
+                };
+            }
+        }
+    }
+
+    fn lay_road(&mut self, x1: i32, x2: i32, y1: i32, y2: i32) {
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Road;
+            }
+        }
+    }
+}
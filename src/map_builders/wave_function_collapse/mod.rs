@@ -0,0 +1,188 @@
+use super::{
+    generate_voronoi_spawn_regions, keep_largest_region, most_distant_tile, MapBuilder,
+    SHOW_MAPGEN_VISUALIZER,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+mod solver;
+use solver::{learn_patterns, learnable_tile, Solver};
+
+const CHUNK_SIZE: i32 = 8;
+/// How many times the solver may run into a contradiction before giving up
+const MAX_ATTEMPTS: i32 = 20;
+/// Results whose largest open area is smaller than this share of the map are
+/// thrown away and tried again
+const MIN_OPEN_FRACTION: f32 = 0.1;
+
+/// Wave function collapse generator: learns which pieces of a sample level fit
+/// together, then assembles a new level out of them. The sample comes from
+/// another builder, which can be a prefab level.
+pub struct WaveFunctionCollapseBuilder {
+    map: Map,
+    starting_position: Position,
+    depth: i32,
+    spawn_list: Vec<(usize, String)>,
+    history: Vec<Vec<TileType>>,
+    sample_builder: Box<dyn MapBuilder>,
+}
+
+impl MapBuilder for WaveFunctionCollapseBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.sample_builder.build_map(rng);
+        let sample = self.sample_builder.get_map();
+        let patterns = learn_patterns(&sample, CHUNK_SIZE);
+
+        let chunks_x = self.map.width / CHUNK_SIZE;
+        let chunks_y = self.map.height / CHUNK_SIZE;
+        let mut cave: Vec<usize> = Vec::new();
+        for _attempt in 0..MAX_ATTEMPTS {
+            let mut solver = Solver::new(&patterns, CHUNK_SIZE, chunks_x, chunks_y);
+            // a contradiction just means trying again
+            if let Some(chosen) = solver.solve(rng) {
+                self.clear();
+                solver.render(&chosen, &mut self.map);
+                self.wall_in_edges();
+                self.take_snapshot();
+
+                cave = keep_largest_region(&mut self.map);
+                let wanted = (self.map.tiles.len() as f32 * MIN_OPEN_FRACTION) as usize;
+                if cave.len() >= wanted {
+                    break;
+                }
+                cave.clear();
+            }
+        }
+
+        // the sample is known to be a working level, so it is better than nothing
+        if cave.is_empty() {
+            self.copy_sample(&sample);
+            cave = keep_largest_region(&mut self.map);
+        }
+        self.take_snapshot();
+
+        // start on the open tile closest to the middle of the map
+        let center = rltk::Point::new(self.map.width / 2, self.map.height / 2);
+        let start_idx = *cave
+            .iter()
+            .min_by_key(|idx| {
+                let x = **idx as i32 % self.map.width;
+                let y = **idx as i32 / self.map.width;
+                (x - center.x).abs() + (y - center.y).abs()
+            })
+            .expect("Wave function collapse produced no floor");
+        if self.map.tiles[start_idx] != TileType::Floor {
+            self.map.tiles[start_idx] = TileType::Floor;
+        }
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+
+        let stairs_idx = most_distant_tile(&mut self.map, start_idx);
+        self.map.tiles[stairs_idx] = TileType::StairsDown;
+        self.take_snapshot();
+
+        let mut noise_areas = generate_voronoi_spawn_regions(&self.map, rng);
+        for area in noise_areas.values_mut() {
+            area.retain(|idx| *idx != start_idx);
+            spawner::spawn_region(rng, area, self.depth, &mut self.spawn_list);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        return self.map.clone();
+    }
+
+    fn get_starting_position(&self) -> Position {
+        return self.starting_position.clone();
+    }
+
+    fn get_spawn_list(&self) -> &Vec<(usize, String)> {
+        return &self.spawn_list;
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Vec<TileType>> {
+        return self.history.clone();
+    }
+
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            self.history.push(self.map.tiles.clone());
+        }
+    }
+}
+
+impl WaveFunctionCollapseBuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        sample_builder: Box<dyn MapBuilder>,
+    ) -> WaveFunctionCollapseBuilder {
+        return WaveFunctionCollapseBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            depth: new_depth,
+            spawn_list: Vec::new(),
+            history: Vec::new(),
+            sample_builder,
+        };
+    }
+
+    fn clear(&mut self) {
+        for tile in self.map.tiles.iter_mut() {
+            *tile = TileType::Wall;
+        }
+    }
+
+    /// Patterns run right up to the edge of the map, which must stay solid
+    fn wall_in_edges(&mut self) {
+        for x in 0..self.map.width {
+            let top = self.map.xy_idx(x, 0);
+            let bottom = self.map.xy_idx(x, self.map.height - 1);
+            self.map.tiles[top] = TileType::Wall;
+            self.map.tiles[bottom] = TileType::Wall;
+        }
+        for y in 0..self.map.height {
+            let left = self.map.xy_idx(0, y);
+            let right = self.map.xy_idx(self.map.width - 1, y);
+            self.map.tiles[left] = TileType::Wall;
+            self.map.tiles[right] = TileType::Wall;
+        }
+    }
+
+    fn copy_sample(&mut self, sample: &Map) {
+        self.clear();
+        for y in 1..i32::min(sample.height, self.map.height) - 1 {
+            for x in 1..i32::min(sample.width, self.map.width) - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = learnable_tile(sample.tiles[sample.xy_idx(x, y)]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::simple_map::SimpleMapBuilder;
+
+    #[test]
+    fn a_copied_sample_leaves_its_stairs_behind() {
+        let mut sample = Map::new(1, 20, 10);
+        for idx in 0..sample.tiles.len() {
+            sample.tiles[idx] = TileType::Floor;
+        }
+        let down_idx = sample.xy_idx(3, 3);
+        let up_idx = sample.xy_idx(15, 6);
+        sample.tiles[down_idx] = TileType::StairsDown;
+        sample.tiles[up_idx] = TileType::StairsUp;
+
+        let mut builder =
+            WaveFunctionCollapseBuilder::new(1, 20, 10, Box::new(SimpleMapBuilder::new(1, 20, 10)));
+        builder.copy_sample(&sample);
+
+        assert_eq!(builder.map.tiles[down_idx], TileType::Floor);
+        assert_eq!(builder.map.tiles[up_idx], TileType::Floor);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{Map, TileType};
+use rltk::RandomNumberGenerator;
+use std::collections::HashMap;
+
+/// A square piece of the sample map, and how often it turned up
+pub struct Pattern {
+    pub tiles: Vec<TileType>,
+    weight: i32,
+    /// Which tiles along each edge are open, as bits; indexed by `Direction`
+    edges: [u32; 4],
+}
+
+#[derive(Copy, Clone)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+impl Direction {
+    fn opposite(self) -> Direction {
+        return match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        };
+    }
+}
+
+impl Pattern {
+    fn new(tiles: Vec<TileType>, weight: i32, chunk_size: i32) -> Pattern {
+        let last = chunk_size - 1;
+        let mut edges = [0u32; 4];
+        for i in 0..chunk_size {
+            let is_open = |x: i32, y: i32| tiles[(y * chunk_size + x) as usize] != TileType::Wall;
+            let bit = 1 << i;
+            if is_open(i, 0) {
+                edges[Direction::North as usize] |= bit;
+            }
+            if is_open(last, i) {
+                edges[Direction::East as usize] |= bit;
+            }
+            if is_open(i, last) {
+                edges[Direction::South as usize] |= bit;
+            }
+            if is_open(0, i) {
+                edges[Direction::West as usize] |= bit;
+            }
+        }
+        return Pattern {
+            tiles,
+            weight,
+            edges,
+        };
+    }
+}
+
+/// Cuts the sample into `chunk_size` squares, along with their mirror images,
+/// and counts how often each distinct one appears
+pub fn learn_patterns(sample: &Map, chunk_size: i32) -> Vec<Pattern> {
+    let mut counts: HashMap<Vec<TileType>, i32> = HashMap::new();
+    let mut order: Vec<Vec<TileType>> = Vec::new();
+
+    for chunk_y in 0..sample.height / chunk_size {
+        for chunk_x in 0..sample.width / chunk_size {
+            for (flip_x, flip_y) in
+                [(false, false), (true, false), (false, true), (true, true)].iter()
+            {
+                let mut tiles = Vec::with_capacity((chunk_size * chunk_size) as usize);
+                for y in 0..chunk_size {
+                    for x in 0..chunk_size {
+                        let sample_x = if *flip_x { chunk_size - 1 - x } else { x };
+                        let sample_y = if *flip_y { chunk_size - 1 - y } else { y };
+                        let idx = sample.xy_idx(
+                            chunk_x * chunk_size + sample_x,
+                            chunk_y * chunk_size + sample_y,
+                        );
+                        tiles.push(learnable_tile(sample.tiles[idx]));
+                    }
+                }
+
+                // keep patterns in the order they were found, so a seed
+                // always produces the same level
+                let count = counts.entry(tiles.clone()).or_insert(0);
+                if *count == 0 {
+                    order.push(tiles);
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    return order
+        .into_iter()
+        .map(|tiles| {
+            let weight = counts[&tiles];
+            Pattern::new(tiles, weight, chunk_size)
+        })
+        .collect();
+}
+
+/// Stairs are placed again after generation, so the sample's own stairs are
+/// read as floor, both when learning and when copying the sample outright
+pub fn learnable_tile(tile: TileType) -> TileType {
+    return match tile {
+        TileType::StairsDown | TileType::StairsUp => TileType::Floor,
+        other => other,
+    };
+}
+
+/// Fills a grid of chunks with patterns whose touching edges agree on where
+/// the open tiles are. Returns `None` if it ran into a contradiction.
+pub struct Solver<'a> {
+    patterns: &'a [Pattern],
+    chunk_size: i32,
+    chunks_x: i32,
+    chunks_y: i32,
+    possible: Vec<Vec<bool>>,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(
+        patterns: &'a [Pattern],
+        chunk_size: i32,
+        chunks_x: i32,
+        chunks_y: i32,
+    ) -> Solver<'a> {
+        return Solver {
+            patterns,
+            chunk_size,
+            chunks_x,
+            chunks_y,
+            possible: vec![vec![true; patterns.len()]; (chunks_x * chunks_y) as usize],
+        };
+    }
+
+    pub fn solve(&mut self, rng: &mut RandomNumberGenerator) -> Option<Vec<usize>> {
+        while let Some(cell) = self.least_uncertain_cell(rng) {
+            let choice = self.weighted_choice(cell, rng);
+            for (pattern, possible) in self.possible[cell].iter_mut().enumerate() {
+                *possible = pattern == choice;
+            }
+            if !self.propagate(cell) {
+                return None;
+            }
+        }
+
+        return Some(
+            self.possible
+                .iter()
+                .map(|cell| cell.iter().position(|possible| *possible).unwrap())
+                .collect(),
+        );
+    }
+
+    /// Writes the chosen patterns into the map, top-left aligned
+    pub fn render(&self, chosen: &[usize], map: &mut Map) {
+        for chunk_y in 0..self.chunks_y {
+            for chunk_x in 0..self.chunks_x {
+                let pattern = &self.patterns[chosen[(chunk_y * self.chunks_x + chunk_x) as usize]];
+                for y in 0..self.chunk_size {
+                    for x in 0..self.chunk_size {
+                        let idx = map
+                            .xy_idx(chunk_x * self.chunk_size + x, chunk_y * self.chunk_size + y);
+                        map.tiles[idx] = pattern.tiles[(y * self.chunk_size + x) as usize];
+                    }
+                }
+            }
+        }
+    }
+
+    /// The undecided cell with the fewest options left, ties broken at random
+    fn least_uncertain_cell(&self, rng: &mut RandomNumberGenerator) -> Option<usize> {
+        let mut best: Vec<usize> = Vec::new();
+        let mut best_count = usize::MAX;
+        for (cell, possible) in self.possible.iter().enumerate() {
+            let count = possible.iter().filter(|p| **p).count();
+            if count <= 1 {
+                continue;
+            }
+            if count < best_count {
+                best_count = count;
+                best.clear();
+            }
+            if count == best_count {
+                best.push(cell);
+            }
+        }
+        return rng.random_slice_entry(&best).copied();
+    }
+
+    fn weighted_choice(&self, cell: usize, rng: &mut RandomNumberGenerator) -> usize {
+        let total: i32 = self.possible[cell]
+            .iter()
+            .enumerate()
+            .filter(|(_pattern, possible)| **possible)
+            .map(|(pattern, _possible)| self.patterns[pattern].weight)
+            .sum();
+
+        let mut roll = rng.roll_dice(1, total) - 1;
+        for (pattern, possible) in self.possible[cell].iter().enumerate() {
+            if !*possible {
+                continue;
+            }
+            if roll < self.patterns[pattern].weight {
+                return pattern;
+            }
+            roll -= self.patterns[pattern].weight;
+        }
+        unreachable!("weighted choice fell off the end of the table");
+    }
+
+    /// Removes options that no longer fit next to a changed cell, spreading
+    /// outwards until nothing changes. Returns false on a contradiction.
+    fn propagate(&mut self, start: usize) -> bool {
+        let mut open_list = vec![start];
+        while let Some(cell) = open_list.pop() {
+            let cell_x = cell as i32 % self.chunks_x;
+            let cell_y = cell as i32 / self.chunks_x;
+
+            for direction in DIRECTIONS.iter() {
+                let (nx, ny) = match direction {
+                    Direction::North => (cell_x, cell_y - 1),
+                    Direction::East => (cell_x + 1, cell_y),
+                    Direction::South => (cell_x, cell_y + 1),
+                    Direction::West => (cell_x - 1, cell_y),
+                };
+                if nx < 0 || nx >= self.chunks_x || ny < 0 || ny >= self.chunks_y {
+                    continue;
+                }
+                let neighbour = (ny * self.chunks_x + nx) as usize;
+
+                // a neighbour's facing edge must match one of this cell's edges
+                let mut edges = vec![false; 1 << self.chunk_size];
+                for (pattern, possible) in self.possible[cell].iter().enumerate() {
+                    if *possible {
+                        edges[self.patterns[pattern].edges[*direction as usize] as usize] = true;
+                    }
+                }
+
+                let facing = direction.opposite() as usize;
+                let mut changed = false;
+                for (pattern, possible) in self.possible[neighbour].iter_mut().enumerate() {
+                    if *possible && !edges[self.patterns[pattern].edges[facing] as usize] {
+                        *possible = false;
+                        changed = true;
+                    }
+                }
+
+                if changed {
+                    if !self.possible[neighbour].iter().any(|p| *p) {
+                        return false;
+                    }
+                    open_list.push(neighbour);
+                }
+            }
+        }
+        return true;
+    }
+}
@@ -1,19 +1,32 @@
 use super::{
-    CombatStats, DefenseBonus, Equipped, GameLog, MeleePowerBonus, Name, SufferDamage, WantsToMelee,
+    combat::{resolve_attack, Attack, DamageType, Dice, HitKind},
+    Attributes, Damage, DefenseBonus, Equipped, GameLog, MeleeWeapon, Name, NaturalAttack, Pools,
+    SufferDamage, WantsToMelee,
 };
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
+/// Fists, for anyone with neither a weapon nor a natural attack
+const UNARMED_DAMAGE: Dice = Dice {
+    n_dice: 1,
+    die_type: 4,
+    bonus: 0,
+};
+
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
-        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Pools>,
         WriteStorage<'a, SufferDamage>,
-        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, MeleeWeapon>,
+        ReadStorage<'a, NaturalAttack>,
         ReadStorage<'a, DefenseBonus>,
         ReadStorage<'a, Equipped>,
     );
@@ -22,57 +35,86 @@ impl<'a> System<'a> for MeleeCombatSystem {
         let (
             entities,
             mut log,
+            mut rng,
             mut wants_melee,
             names,
-            combat_stats,
+            attributes,
+            pools,
             mut inflict_damage,
-            melee_power_bonuses,
+            melee_weapons,
+            natural_attacks,
             defense_bonuses,
             equipped,
         ) = data;
 
-        for (entity, wants_melee, name, stats) in
-            (&entities, &wants_melee, &names, &combat_stats).join()
+        for (entity, wants_melee, name, attacker_attributes, attacker_pools) in
+            (&entities, &wants_melee, &names, &attributes, &pools).join()
         {
-            if stats.hp > 0 {
-                let mut offensive_bonus = 0;
-                for (_e, power_bonus, equipped_by) in
-                    (&entities, &melee_power_bonuses, &equipped).join()
-                {
+            if attacker_pools.hit_points.current > 0 {
+                // a wielded weapon beats claws and teeth, which beat fists
+                let (mut weapon_dice, mut damage_type) = match natural_attacks.get(entity) {
+                    Some(natural_attack) => (natural_attack.damage, natural_attack.damage_type),
+                    None => (UNARMED_DAMAGE, DamageType::Bludgeoning),
+                };
+                let mut weapon_hit_bonus = 0;
+                for (weapon, equipped_by) in (&melee_weapons, &equipped).join() {
                     if equipped_by.owner == entity {
-                        offensive_bonus += power_bonus.power;
+                        weapon_dice = weapon.damage;
+                        damage_type = weapon.damage_type;
+                        weapon_hit_bonus = weapon.hit_bonus;
                     }
                 }
 
-                let target_stats = combat_stats.get(wants_melee.target).unwrap();
-                if target_stats.hp > 0 {
+                let target_pools = pools.get(wants_melee.target).unwrap();
+                if target_pools.hit_points.current > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
+                    let target_attributes = attributes.get(wants_melee.target).unwrap();
 
-                    let mut defensive_bonus = 0;
-                    for (_e, defense_bonus, equipped_by) in
-                        (&entities, &defense_bonuses, &equipped).join()
-                    {
-                        if equipped_by.owner == entity {
-                            defensive_bonus += defense_bonus.defense;
+                    let mut armor_class = target_attributes.armor_class();
+                    for (defense_bonus, equipped_by) in (&defense_bonuses, &equipped).join() {
+                        if equipped_by.owner == wants_melee.target {
+                            armor_class += defense_bonus.defense;
                         }
                     }
 
-                    let damage = i32::max(
-                        0,
-                        (stats.power + offensive_bonus) - (target_stats.defense + defensive_bonus),
-                    );
+                    let might_bonus = attacker_attributes.might.bonus;
+                    let attack = Attack {
+                        hit_bonus: might_bonus + weapon_hit_bonus,
+                        damage: Dice {
+                            bonus: weapon_dice.bonus + might_bonus,
+                            ..weapon_dice
+                        },
+                    };
+                    let result = resolve_attack(&attack, armor_class, &mut rng);
 
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{} is unable to hurt {}",
+                    // the damage system reports hits, once resistances are known
+                    let message = match result.kind {
+                        HitKind::Fumble => Some(format!(
+                            "{} fumbles the attack on {}.",
+                            &name.value, &target_name.value
+                        )),
+                        HitKind::Miss => {
+                            Some(format!("{} misses {}.", &name.value, &target_name.value))
+                        }
+                        HitKind::Hit => None,
+                        HitKind::Critical => Some(format!(
+                            "{} lands a critical hit on {}!",
                             &name.value, &target_name.value
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {} for {} hp.",
-                            &name.value, &target_name.value, damage
-                        ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        )),
+                    };
+                    if let Some(message) = message {
+                        log.entries.push(message);
+                    }
+                    if log.verbose {
+                        log.entries.push(format!("  ({})", result.breakdown()));
+                    }
+
+                    if result.is_hit() {
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_melee.target,
+                            Damage::new(result.damage, damage_type, &name.value),
+                        );
                     }
                 }
             }
@@ -1,4 +1,8 @@
-use super::{Confusion, Map, Monster, Position, RunState, Viewshed, WantsToMelee};
+use super::{
+    raws::{raws, Reaction},
+    Confusion, EntityMoved, Faction, Map, Monster, Position, RunState, TileType, Viewshed, Wading,
+    WantsToMelee,
+};
 use rltk::Point;
 use specs::prelude::*;
 
@@ -7,8 +11,6 @@ pub struct MonsterAI {}
 impl<'a> System<'a> for MonsterAI {
     type SystemData = (
         WriteExpect<'a, Map>,
-        ReadExpect<'a, Point>,
-        ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
@@ -16,13 +18,14 @@ impl<'a> System<'a> for MonsterAI {
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
         WriteStorage<'a, Confusion>,
+        WriteStorage<'a, Wading>,
+        WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, Faction>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut map,
-            player_pos,
-            player_entity,
             runstate,
             entities,
             mut viewshed,
@@ -30,13 +33,17 @@ impl<'a> System<'a> for MonsterAI {
             mut position,
             mut wants_to_melee,
             mut confusions,
+            mut wading,
+            mut entity_moved,
+            factions,
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
 
-        for (entity, mut viewshed, _monster, mut pos) in
+        let mut door_opened = false;
+        for (entity, viewshed, _monster, pos) in
             (&entities, &mut viewshed, &monster, &mut position).join()
         {
             let mut can_act = true;
@@ -49,38 +56,107 @@ impl<'a> System<'a> for MonsterAI {
                 can_act = false;
             }
 
-            if can_act {
-                let distance =
-                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+            if !can_act {
+                continue;
+            }
+
+            let my_pos = Point::new(pos.x, pos.y);
+            let target = match factions.get(entity) {
+                Some(faction) => {
+                    nearest_enemy(&map, &factions, entity, &faction.name, my_pos, viewshed)
+                }
+                None => None,
+            };
+
+            if let Some((target, target_pos)) = target {
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(my_pos, target_pos);
 
                 if distance < 1.5 {
                     wants_to_melee
-                        .insert(
-                            entity,
-                            WantsToMelee {
-                                target: *player_entity,
-                            },
-                        )
+                        .insert(entity, WantsToMelee { target })
                         .expect("Unable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                    // Path to the player
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y),
-                        map.xy_idx(player_pos.x, player_pos.y),
-                        &mut *map,
-                    );
+                } else {
+                    // the target itself blocks its tile, which would keep the
+                    // path from ever reaching it
+                    let target_idx = map.xy_idx(target_pos.x, target_pos.y);
+                    let target_blocked = map.blocked_tiles[target_idx];
+                    map.blocked_tiles[target_idx] = false;
+                    let path = rltk::a_star_search(map.xy_idx(pos.x, pos.y), target_idx, &*map);
+                    map.blocked_tiles[target_idx] = target_blocked;
 
-                    if path.success && path.steps.len() > 1 {
+                    if path.success
+                        && path.steps.len() > 1
+                        && matches!(
+                            map.tiles[path.steps[1]],
+                            TileType::DoorClosed | TileType::SecretDoor
+                        )
+                    {
+                        // opening the door takes the monster's turn, and
+                        // gives away a secret one
+                        map.tiles[path.steps[1]] = TileType::DoorOpen;
+                        door_opened = true;
+                    } else if path.success && path.steps.len() > 1 {
+                        // wading out of shallow water takes the whole turn
+                        if wading.remove(entity).is_some() {
+                            continue;
+                        }
+                        if map.tiles[path.steps[1]] == TileType::ShallowWater {
+                            wading
+                                .insert(entity, Wading {})
+                                .expect("Unable to insert wading");
+                        }
                         let mut idx = map.xy_idx(pos.x, pos.y);
                         map.blocked_tiles[idx] = false;
                         pos.x = path.steps[1] as i32 % map.width;
                         pos.y = path.steps[1] as i32 / map.width;
                         idx = map.xy_idx(pos.x, pos.y);
                         map.blocked_tiles[idx] = true;
+                        entity_moved
+                            .insert(entity, EntityMoved {})
+                            .expect("Unable to insert marker");
                         viewshed.dirty = true;
                     }
                 }
             }
         }
+
+        // an opened door changes what everybody can see
+        if door_opened {
+            for viewshed in (&mut viewshed).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
+
+/// The closest visible entity that `faction` wants to attack, and where it is
+fn nearest_enemy(
+    map: &Map,
+    factions: &ReadStorage<Faction>,
+    me: Entity,
+    faction: &str,
+    my_pos: Point,
+    viewshed: &Viewshed,
+) -> Option<(Entity, Point)> {
+    let mut nearest: Option<(Entity, Point, f32)> = None;
+    for tile in viewshed.visible_tiles.iter() {
+        let idx = map.xy_idx(tile.x, tile.y);
+        for other in map.tile_content[idx].iter() {
+            if *other == me {
+                continue;
+            }
+            let their_faction = match factions.get(*other) {
+                Some(their_faction) => their_faction,
+                None => continue,
+            };
+            if raws().faction_reaction(faction, &their_faction.name) != Reaction::Attack {
+                continue;
+            }
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(my_pos, *tile);
+            if nearest.is_none_or(|(_, _, best)| distance < best) {
+                nearest = Some((*other, *tile, distance));
+            }
+        }
     }
+    return nearest.map(|(entity, pos, _)| (entity, pos));
 }
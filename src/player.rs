@@ -1,19 +1,52 @@
 use super::{
-    CombatStats, GameLog, Item, Map, Monster, Player, Position, RunState, State, TileType,
-    Viewshed, WantsToMelee, WantsToPickUpItem,
+    Ammunition, Attributes, Confusion, EntityMoved, Equipped, GameLog, Hidden, InBackpack, Item,
+    Map, Monster, Name, Player, Pools, Position, RangedWeapon, RunState, State, TileType, Viewshed,
+    Wading, WantsToMelee, WantsToPickUpItem,
 };
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Searching finds each hidden thing within this many tiles one time in SEARCH_CHANCE
+const SEARCH_RADIUS: i32 = 2;
+const SEARCH_CHANCE: i32 = 3;
+
+/// A random step to one of the eight neighbouring tiles; never standing still,
+/// which would have the player attack their own tile
+fn stagger_direction(rng: &mut RandomNumberGenerator) -> (i32, i32) {
+    loop {
+        let delta = (rng.range(-1, 2), rng.range(-1, 2));
+        if delta != (0, 0) {
+            return delta;
+        }
+    }
+}
+
+pub fn try_move_player(mut delta_x: i32, mut delta_y: i32, ecs: &mut World) {
+    // a confused player staggers off in a random direction
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let mut confusion = ecs.write_storage::<Confusion>();
+        if let Some(confused) = confusion.get_mut(*player_entity) {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            (delta_x, delta_y) = stagger_direction(&mut rng);
+            confused.turns -= 1;
+            if confused.turns < 1 {
+                confusion.remove(*player_entity);
+            }
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("You stumble around.".to_string());
+        }
+    }
+
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let entities = ecs.entities();
-    let combat_stats = ecs.read_storage::<CombatStats>();
-    let map = ecs.fetch::<Map>();
+    let pools = ecs.read_storage::<Pools>();
+    let mut map = ecs.fetch_mut::<Map>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut wading = ecs.write_storage::<Wading>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -28,7 +61,10 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
-            let target = combat_stats.get(*potential_target);
+            if *potential_target == entity {
+                continue;
+            }
+            let target = pools.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee
                     .insert(
@@ -42,10 +78,30 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             }
         }
 
-        if !map.blocked_tiles[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        if map.tiles[destination_idx] == TileType::DoorClosed {
+            map.tiles[destination_idx] = TileType::DoorOpen;
+            viewshed.dirty = true;
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("You open the door.".to_string());
+        } else if map.tiles[destination_idx] == TileType::SecretDoor {
+            // feels just like the rest of the wall until someone finds it
+        } else if !map.blocked_tiles[destination_idx] && wading.remove(entity).is_some() {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog
+                .entries
+                .push("You wade through the water.".to_string());
+        } else if !map.blocked_tiles[destination_idx] {
+            if map.tiles[destination_idx] == TileType::ShallowWater {
+                wading
+                    .insert(entity, Wading {})
+                    .expect("Unable to insert wading");
+            }
+            pos.x = (pos.x + delta_x).clamp(0, map.width - 1);
+            pos.y = (pos.y + delta_y).clamp(0, map.height - 1);
 
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
             viewshed.dirty = true;
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
@@ -100,12 +156,28 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
 
             // Interact
             VirtualKeyCode::F => get_item(&mut gs.ecs),
+            VirtualKeyCode::O => {
+                if !toggle_adjacent_doors(&mut gs.ecs, TileType::DoorClosed) {
+                    return RunState::AwaitingInput;
+                }
+            }
+            VirtualKeyCode::C => {
+                if !toggle_adjacent_doors(&mut gs.ecs, TileType::DoorOpen) {
+                    return RunState::AwaitingInput;
+                }
+            }
             VirtualKeyCode::Period => {
                 if try_next_level(&mut gs.ecs) {
                     return RunState::NextLevel;
                 }
             }
+            VirtualKeyCode::Comma => {
+                if try_previous_level(&mut gs.ecs) {
+                    return RunState::PreviousLevel;
+                }
+            }
             VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
+            VirtualKeyCode::T => return start_firing(&mut gs.ecs),
 
             // Menus
             VirtualKeyCode::D => return RunState::ShowDropItem,
@@ -113,12 +185,127 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             VirtualKeyCode::R => return RunState::ShowRemoveEquipment,
             VirtualKeyCode::Escape => return RunState::SaveGame,
 
+            // Options
+            VirtualKeyCode::V => {
+                toggle_verbose_combat(&mut gs.ecs);
+                return RunState::AwaitingInput;
+            }
+
             _ => return RunState::AwaitingInput,
         },
     }
     return RunState::PlayerTurn;
 }
 
+/// Starts aiming the equipped ranged weapon, if there is one and something
+/// to shoot from it
+fn start_firing(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let names = ecs.read_storage::<Name>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let weapon = (&ranged_weapons, &equipped)
+        .join()
+        .find(|(_weapon, equipped_by)| equipped_by.owner == *player_entity)
+        .map(|(weapon, _equipped_by)| weapon);
+    let weapon = match weapon {
+        Some(weapon) => weapon,
+        None => {
+            gamelog
+                .entries
+                .push("You have no ranged weapon ready.".to_string());
+            return RunState::AwaitingInput;
+        }
+    };
+
+    let has_ammo = (&backpack, &names, &ammunition)
+        .join()
+        .any(|(pack, name, _ammo)| pack.owner == *player_entity && name.value == weapon.ammo);
+    if !has_ammo {
+        gamelog
+            .entries
+            .push(format!("You are out of {}.", weapon.ammo));
+        return RunState::AwaitingInput;
+    }
+
+    return RunState::ShowFireTargeting {
+        range: weapon.range,
+        selected: 0,
+    };
+}
+
+/// Shows or hides the dice behind every attack. Takes no time.
+fn toggle_verbose_combat(ecs: &mut World) {
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.verbose = !gamelog.verbose;
+    let message = if gamelog.verbose {
+        "Combat rolls will be shown."
+    } else {
+        "Combat rolls will be hidden."
+    };
+    gamelog.entries.push(message.to_string());
+}
+
+/// Opens (or closes) every closed (or open) door next to the player.
+/// Returns false if there was nothing to do, so no turn is spent.
+fn toggle_adjacent_doors(ecs: &mut World, from: TileType) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let player_entity = ecs.fetch::<Entity>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut map = ecs.fetch_mut::<Map>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let (to, verb) = if from == TileType::DoorClosed {
+        (TileType::DoorOpen, "open")
+    } else {
+        (TileType::DoorClosed, "close")
+    };
+
+    let mut toggled = 0;
+    let mut blocked = false;
+    for y in player_pos.y - 1..=player_pos.y + 1 {
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] != from {
+                continue;
+            }
+            // nothing can be shut in a doorway that someone is standing in
+            if to == TileType::DoorClosed && !map.tile_content[idx].is_empty() {
+                blocked = true;
+                continue;
+            }
+            map.tiles[idx] = to;
+            toggled += 1;
+        }
+    }
+
+    if toggled > 0 {
+        if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+            viewshed.dirty = true;
+        }
+        gamelog.entries.push(format!("You {} the door.", verb));
+        return true;
+    }
+
+    if blocked {
+        gamelog
+            .entries
+            .push("Something is in the way of the door.".to_string());
+    } else {
+        gamelog
+            .entries
+            .push(format!("There is no door here to {}.", verb));
+    }
+    return false;
+}
+
 fn try_next_level(ecs: &mut World) -> bool {
     let player_pos = ecs.fetch::<Point>();
     let map = ecs.fetch::<Map>();
@@ -135,7 +322,62 @@ fn try_next_level(ecs: &mut World) -> bool {
     }
 }
 
+fn try_previous_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+    if map.tiles[player_idx] == TileType::StairsUp {
+        return true;
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way up from here.".to_string());
+        return false;
+    }
+}
+
+/// Looks around for hidden traps and secret doors near the player
+fn search(ecs: &mut World) {
+    let player_pos = ecs.fetch::<Point>();
+    let mut map = ecs.fetch_mut::<Map>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let names = ecs.read_storage::<Name>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    for y in player_pos.y - SEARCH_RADIUS..=player_pos.y + SEARCH_RADIUS {
+        for x in player_pos.x - SEARCH_RADIUS..=player_pos.x + SEARCH_RADIUS {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            if !map.visible_tiles[idx] {
+                continue;
+            }
+
+            if map.tiles[idx] == TileType::SecretDoor && rng.roll_dice(1, SEARCH_CHANCE) == 1 {
+                map.tiles[idx] = TileType::DoorClosed;
+                gamelog.entries.push("You find a secret door!".to_string());
+            }
+
+            for entity in map.tile_content[idx].iter() {
+                if hidden.get(*entity).is_some() && rng.roll_dice(1, SEARCH_CHANCE) == 1 {
+                    hidden.remove(*entity);
+                    if let Some(name) = names.get(*entity) {
+                        gamelog.entries.push(format!("You spot a {}!", name.value));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Waits a turn, searching the area and healing if no monsters are in sight
 fn skip_turn(ecs: &mut World) -> RunState {
+    search(ecs);
+
     let player_entity = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
     let monsters = ecs.read_storage::<Monster>();
@@ -146,6 +388,9 @@ fn skip_turn(ecs: &mut World) -> RunState {
     let viewshed = viewshed_components.get(*player_entity).unwrap();
     for tile in viewshed.visible_tiles.iter() {
         let idx = world_map_resource.xy_idx(tile.x, tile.y);
+        if !world_map_resource.is_lit(idx) {
+            continue;
+        }
         for entity in world_map_resource.tile_content[idx].iter() {
             // check if any monsters are in view
             let mob = monsters.get(*entity);
@@ -157,13 +402,33 @@ fn skip_turn(ecs: &mut World) -> RunState {
     }
 
     if can_heal {
-        let mut health_components = ecs.write_storage::<CombatStats>();
-        let player_hp = health_components.get_mut(*player_entity).unwrap();
-        player_hp.hp = i32::min(player_hp.hp + 1, player_hp.max_hp);
+        // fitter characters get their breath back faster
+        let attributes = ecs.read_storage::<Attributes>();
+        let fitness_bonus = attributes.get(*player_entity).unwrap().fitness.bonus;
+        let mut pools = ecs.write_storage::<Pools>();
+        let player_pools = pools.get_mut(*player_entity).unwrap();
+        let hit_points_before = player_pools.hit_points.current;
+        player_pools.hit_points.restore(i32::max(1, fitness_bonus));
+        if player_pools.hit_points.current > hit_points_before {
+            let mut gamelog = ecs.write_resource::<GameLog>();
+            gamelog.entries.push("You catch your breath.".to_string());
+        }
     }
 
-    let mut gamelog = ecs.write_resource::<GameLog>();
-    gamelog.entries.push("You catch your breath.".to_string());
-
     return RunState::PlayerTurn;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_confused_step_never_stays_put() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        for _ in 0..1000 {
+            let (delta_x, delta_y) = stagger_direction(&mut rng);
+            assert_ne!((delta_x, delta_y), (0, 0));
+            assert!((-1..=1).contains(&delta_x) && (-1..=1).contains(&delta_y));
+        }
+    }
+}
@@ -1,60 +1,222 @@
 use rltk::RandomNumberGenerator;
 
-pub struct RandomEntry {
-    name: String,
+/// What an entry gives when it is rolled: a value, or a roll on another table
+#[derive(Clone)]
+enum Outcome<T> {
+    Value(T),
+    Table(RandomTable<T>),
+}
+
+/// One line of a `RandomTable`. Its weight at a given depth is
+/// `weight + per_depth * depth`, and zero outside `min_depth..=max_depth`.
+#[derive(Clone)]
+pub struct RandomEntry<T> {
+    outcome: Outcome<T>,
     weight: i32,
+    per_depth: f32,
+    min_depth: i32,
+    max_depth: i32,
 }
 
-impl RandomEntry {
-    pub fn new<S: ToString>(name: S, weight: i32) -> RandomEntry {
+impl<T: Clone> RandomEntry<T> {
+    pub fn new(value: T, weight: i32) -> RandomEntry<T> {
+        return RandomEntry::with_outcome(Outcome::Value(value), weight);
+    }
+
+    /// An entry that rolls again on `table`, such as "any potion"
+    pub fn table(table: RandomTable<T>, weight: i32) -> RandomEntry<T> {
+        return RandomEntry::with_outcome(Outcome::Table(table), weight);
+    }
+
+    fn with_outcome(outcome: Outcome<T>, weight: i32) -> RandomEntry<T> {
         return RandomEntry {
-            name: name.to_string(),
+            outcome,
             weight,
+            per_depth: 0.0,
+            min_depth: i32::MIN,
+            max_depth: i32::MAX,
         };
     }
+
+    /// Adds this much weight per level of depth; negative values make the
+    /// entry rarer the deeper it goes
+    pub fn per_depth(mut self, per_depth: f32) -> RandomEntry<T> {
+        self.per_depth = per_depth;
+        return self;
+    }
+
+    pub fn depths(mut self, min_depth: i32, max_depth: i32) -> RandomEntry<T> {
+        self.min_depth = min_depth;
+        self.max_depth = max_depth;
+        return self;
+    }
+
+    /// A sub-table with nothing in it at this depth weighs nothing, so it
+    /// cannot crowd out the entries that do have something to give
+    pub fn weight_at(&self, depth: i32) -> i32 {
+        if depth < self.min_depth || depth > self.max_depth {
+            return 0;
+        }
+        if let Outcome::Table(table) = &self.outcome {
+            if table.total_weight(depth) == 0 {
+                return 0;
+            }
+        }
+        let weight = self.weight + (self.per_depth * depth as f32) as i32;
+        return i32::max(0, weight);
+    }
+}
+
+/// Picks one of its entries at random, in proportion to their weights
+#[derive(Clone)]
+pub struct RandomTable<T> {
+    entries: Vec<RandomEntry<T>>,
 }
 
-#[derive(Default)]
-pub struct RandomTable {
-    entries: Vec<RandomEntry>,
-    total_weight: i32,
+impl<T: Clone> Default for RandomTable<T> {
+    fn default() -> RandomTable<T> {
+        return RandomTable::new();
+    }
 }
 
-impl RandomTable {
-    pub fn new() -> RandomTable {
+impl<T: Clone> RandomTable<T> {
+    pub fn new() -> RandomTable<T> {
         return RandomTable {
             entries: Vec::new(),
-            total_weight: 0,
         };
     }
 
-    pub fn add<S: ToString>(mut self, name: S, weight: i32) -> RandomTable {
-        if weight > 0 {
-            self.total_weight += weight;
-            self.entries
-                .push(RandomEntry::new(name.to_string(), weight));
-        }
+    /// Adds a value with the same weight at every depth
+    pub fn add(self, value: T, weight: i32) -> RandomTable<T> {
+        return self.add_entry(RandomEntry::new(value, weight));
+    }
 
+    pub fn add_entry(mut self, entry: RandomEntry<T>) -> RandomTable<T> {
+        self.entries.push(entry);
         return self;
     }
 
-    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> String {
-        if self.total_weight == 0 {
-            return "None".to_string();
-        }
+    pub fn total_weight(&self, depth: i32) -> i32 {
+        return self
+            .entries
+            .iter()
+            .map(|entry| entry.weight_at(depth))
+            .sum();
+    }
 
-        let mut roll = rng.roll_dice(1, self.total_weight) - 1;
-        let mut index: usize = 0;
+    /// Rolls for the given depth. Returns None if nothing can be found there.
+    pub fn roll(&self, depth: i32, rng: &mut RandomNumberGenerator) -> Option<T> {
+        let weights: Vec<i32> = self
+            .entries
+            .iter()
+            .map(|entry| entry.weight_at(depth))
+            .collect();
+        let total_weight: i32 = weights.iter().sum();
+        if total_weight == 0 {
+            return None;
+        }
 
-        while roll > 0 {
-            if roll < self.entries[index].weight {
-                return self.entries[index].name.clone();
+        let mut roll = rng.roll_dice(1, total_weight) - 1;
+        for (entry, weight) in self.entries.iter().zip(weights.iter()) {
+            if roll < *weight {
+                return match &entry.outcome {
+                    Outcome::Value(value) => Some(value.clone()),
+                    Outcome::Table(table) => table.roll(depth, rng),
+                };
             }
-
-            roll -= self.entries[index].weight;
-            index += 1;
+            roll -= weight;
         }
 
-        return "None".to_string();
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLLS: usize = 20_000;
+
+    /// How often each value came up, as a fraction of all rolls
+    fn frequency(table: &RandomTable<&str>, depth: i32, value: &str) -> f32 {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        let hits = (0..ROLLS)
+            .filter(|_| table.roll(depth, &mut rng) == Some(value))
+            .count();
+        return hits as f32 / ROLLS as f32;
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.02,
+            "expected about {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn a_single_entry_is_always_picked() {
+        // a roll of zero used to fall straight through to "nothing"
+        let table = RandomTable::new().add("Goblin", 1);
+        assert_close(frequency(&table, 1, "Goblin"), 1.0);
+    }
+
+    #[test]
+    fn an_empty_table_rolls_nothing() {
+        let table: RandomTable<&str> = RandomTable::new().add("Goblin", 0);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(table.roll(1, &mut rng), None);
+    }
+
+    #[test]
+    fn entries_are_picked_in_proportion_to_their_weight() {
+        let table = RandomTable::new()
+            .add("Goblin", 6)
+            .add("Orc", 3)
+            .add("Dragon", 1);
+        assert_close(frequency(&table, 1, "Goblin"), 0.6);
+        assert_close(frequency(&table, 1, "Orc"), 0.3);
+        assert_close(frequency(&table, 1, "Dragon"), 0.1);
+    }
+
+    #[test]
+    fn weights_follow_depth() {
+        let table = RandomTable::new()
+            .add("Goblin", 4)
+            .add_entry(RandomEntry::new("Orc", 0).per_depth(1.0))
+            .add_entry(RandomEntry::new("Dragon", 1).depths(5, 10));
+
+        assert_close(frequency(&table, 1, "Orc"), 0.2);
+        assert_close(frequency(&table, 4, "Orc"), 0.5);
+        assert_eq!(frequency(&table, 4, "Dragon"), 0.0);
+        assert_close(frequency(&table, 6, "Dragon"), 1.0 / 11.0);
+        assert_eq!(frequency(&table, 11, "Dragon"), 0.0);
+    }
+
+    #[test]
+    fn sub_tables_split_their_share() {
+        let potions = RandomTable::new()
+            .add("Health Potion", 3)
+            .add("Mana Potion", 1);
+        let table = RandomTable::new()
+            .add("Dagger", 1)
+            .add_entry(RandomEntry::table(potions, 1));
+
+        assert_close(frequency(&table, 1, "Dagger"), 0.5);
+        assert_close(frequency(&table, 1, "Health Potion"), 0.375);
+        assert_close(frequency(&table, 1, "Mana Potion"), 0.125);
+    }
+
+    #[test]
+    fn sub_tables_with_nothing_at_this_depth_are_skipped() {
+        let deep_potions = RandomTable::new()
+            .add_entry(RandomEntry::new("Potion of Giant Strength", 1).depths(5, 10));
+        let table = RandomTable::new()
+            .add("Dagger", 1)
+            .add_entry(RandomEntry::table(deep_potions, 9));
+
+        assert_eq!(frequency(&table, 1, "Dagger"), 1.0);
+        assert_close(frequency(&table, 5, "Dagger"), 0.1);
     }
 }
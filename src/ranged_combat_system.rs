@@ -0,0 +1,199 @@
+use super::{
+    combat::{resolve_attack, Attack, HitKind},
+    Ammunition, Attributes, Damage, DefenseBonus, Equipped, GameLog, InBackpack, Item, Map, Name,
+    Pools, Position, RangedWeapon, Renderable, SerializeMe, SufferDamage, WantsToShoot,
+};
+use rltk::{BaseMap, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadExpect<'a, Map>,
+        Read<'a, LazyUpdate>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Pools>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, RangedWeapon>,
+        WriteStorage<'a, Ammunition>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Equipped>,
+        WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut log,
+            mut rng,
+            map,
+            lazy,
+            mut wants_shoot,
+            names,
+            attributes,
+            pools,
+            mut inflict_damage,
+            ranged_weapons,
+            mut ammunition,
+            defense_bonuses,
+            equipped,
+            mut backpack,
+            mut positions,
+            renderables,
+        ) = data;
+
+        let mut shots: Vec<(Entity, Point)> = Vec::new();
+        for (entity, shoot) in (&entities, &wants_shoot).join() {
+            shots.push((entity, shoot.target));
+        }
+        wants_shoot.clear();
+
+        for (shooter, target) in shots {
+            let weapon = (&ranged_weapons, &equipped)
+                .join()
+                .find(|(_weapon, equipped_by)| equipped_by.owner == shooter)
+                .map(|(weapon, _equipped_by)| weapon.clone());
+            let weapon = match weapon {
+                Some(weapon) => weapon,
+                None => continue,
+            };
+            let ammo_stack = (&entities, &backpack, &names, &ammunition)
+                .join()
+                .find(|(_entity, pack, name, _ammo)| {
+                    pack.owner == shooter && name.value == weapon.ammo
+                })
+                .map(|(entity, _pack, _name, _ammo)| entity);
+            let ammo_stack = match ammo_stack {
+                Some(ammo_stack) => ammo_stack,
+                None => continue,
+            };
+            let start = match positions.get(shooter) {
+                Some(pos) => Point::new(pos.x, pos.y),
+                None => continue,
+            };
+            let shooter_name = &names.get(shooter).unwrap().value;
+            let hit_bonus = attributes
+                .get(shooter)
+                .map_or(0, |attributes| attributes.quickness.bonus);
+            let attack = Attack {
+                hit_bonus: hit_bonus + weapon.hit_bonus,
+                damage: weapon.damage,
+            };
+
+            // the missile flies on past whatever it misses, until it hits
+            // something, strikes a wall or runs out of range
+            let mut landed_at = start;
+            for step in flight_path(start, target, weapon.range) {
+                if step.x < 1 || step.x > map.width - 1 || step.y < 1 || step.y > map.height - 1 {
+                    break;
+                }
+                let idx = map.xy_idx(step.x, step.y);
+                if !map.tiles[idx].is_walkable() || map.is_opaque(idx) {
+                    break;
+                }
+                landed_at = step;
+
+                let victim = map.tile_content[idx].iter().copied().find(|victim| {
+                    *victim != shooter
+                        && pools
+                            .get(*victim)
+                            .is_some_and(|pools| pools.hit_points.current > 0)
+                });
+                let victim = match victim {
+                    Some(victim) => victim,
+                    None => continue,
+                };
+                let victim_name = &names.get(victim).unwrap().value;
+                let mut armor_class = attributes
+                    .get(victim)
+                    .map_or(0, |attributes| attributes.armor_class());
+                for (defense_bonus, equipped_by) in (&defense_bonuses, &equipped).join() {
+                    if equipped_by.owner == victim {
+                        armor_class += defense_bonus.defense;
+                    }
+                }
+
+                let result = resolve_attack(&attack, armor_class, &mut rng);
+                // the damage system reports hits, once resistances are known
+                let message = match result.kind {
+                    HitKind::Fumble => Some(format!("{} fumbles the shot.", shooter_name)),
+                    HitKind::Miss => Some(format!(
+                        "{} shoots at {} and misses.",
+                        shooter_name, victim_name
+                    )),
+                    HitKind::Hit => None,
+                    HitKind::Critical => Some(format!(
+                        "{} lands a critical shot on {}!",
+                        shooter_name, victim_name
+                    )),
+                };
+                if let Some(message) = message {
+                    log.entries.push(message);
+                }
+                if log.verbose {
+                    log.entries.push(format!("  ({})", result.breakdown()));
+                }
+                if result.is_hit() {
+                    SufferDamage::new_damage(
+                        &mut inflict_damage,
+                        victim,
+                        Damage::new(result.damage, weapon.damage_type, shooter_name),
+                    );
+                }
+                if result.is_hit() || result.kind == HitKind::Fumble {
+                    break;
+                }
+            }
+
+            // what was shot lands on the floor, where it can be picked up
+            let landed_at = Position {
+                x: landed_at.x,
+                y: landed_at.y,
+            };
+            let stack = ammunition.get_mut(ammo_stack).unwrap();
+            if stack.count > 1 {
+                stack.count -= 1;
+                lazy.create_entity(&entities)
+                    .with(landed_at)
+                    .with(names.get(ammo_stack).unwrap().clone())
+                    .with(renderables.get(ammo_stack).unwrap().clone())
+                    .with(Item {})
+                    .with(Ammunition { count: 1 })
+                    .marked::<SimpleMarker<SerializeMe>>()
+                    .build();
+            } else {
+                backpack.remove(ammo_stack);
+                positions
+                    .insert(ammo_stack, landed_at)
+                    .expect("Unable to insert position");
+            }
+        }
+    }
+}
+
+/// The tiles a missile passes through on its way from `start` towards
+/// `target`, carrying on past it until `range` runs out
+fn flight_path(start: Point, target: Point, range: i32) -> Vec<Point> {
+    let distance = rltk::DistanceAlg::Pythagoras.distance2d(start, target);
+    if distance < 1.0 {
+        return Vec::new();
+    }
+    let scale = range as f32 / distance;
+    let end = Point::new(
+        start.x + ((target.x - start.x) as f32 * scale).round() as i32,
+        start.y + ((target.y - start.y) as f32 * scale).round() as i32,
+    );
+    return rltk::line2d(rltk::LineAlg::Bresenham, start, end)
+        .into_iter()
+        .filter(|step| *step != start)
+        .collect();
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::sync::OnceLock;
+mod raw_structs;
+use raw_structs::Raws;
+pub use raw_structs::Reaction;
+mod rawmaster;
+pub use rawmaster::RawMaster;
+
+/// Monsters, items and spawn tables live here, so they can be changed without
+/// recompiling
+const RAWS_PATH: &str = "raws/spawns.json";
+
+static RAWS: OnceLock<RawMaster> = OnceLock::new();
+
+/// The game's content, loaded the first time it is needed.
+/// Panics if the raws file is missing or invalid.
+pub fn raws() -> &'static RawMaster {
+    return RAWS.get_or_init(|| match load_raws(RAWS_PATH) {
+        Ok(raws) => raws,
+        Err(error) => panic!("Unable to load {}: {}", RAWS_PATH, error),
+    });
+}
+
+pub fn load_raws(path: &str) -> Result<RawMaster, String> {
+    let json = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    return parse_raws(&json);
+}
+
+pub fn parse_raws(json: &str) -> Result<RawMaster, String> {
+    let raws: Raws = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    return RawMaster::new(raws);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::raw_structs::{
+        RawAttributes, RawEffects, RawEquippable, RawFaction, RawItem, RawLoot, RawMonster,
+        RawRenderable, RawSpawnEntry,
+    };
+    use super::*;
+    use crate::combat::DamageType;
+    use crate::{EquipmentSlot, LootTable};
+    use std::collections::HashMap;
+
+    fn renderable(glyph: &str) -> RawRenderable {
+        return RawRenderable {
+            glyph: glyph.to_string(),
+            fg: "#FF00FF".to_string(),
+            order: 2,
+        };
+    }
+
+    fn item(name: &str, glyph: &str) -> RawItem {
+        return RawItem {
+            name: name.to_string(),
+            renderable: renderable(glyph),
+            consumable: false,
+            range: None,
+            effects: RawEffects::default(),
+            equippable: None,
+            ammunition: None,
+        };
+    }
+
+    fn spawn(name: &str) -> RawSpawnEntry {
+        return RawSpawnEntry {
+            name: Some(name.to_string()),
+            table: None,
+            weight: 1,
+            per_depth: 0.0,
+            min_depth: None,
+            max_depth: None,
+        };
+    }
+
+    fn roll_on(table: &str) -> RawSpawnEntry {
+        return RawSpawnEntry {
+            name: None,
+            table: Some(table.to_string()),
+            ..spawn("")
+        };
+    }
+
+    /// Just a health potion, which is also the only thing that spawns
+    fn minimal_raws() -> Raws {
+        let mut potion = item("Health Potion", "!");
+        potion.consumable = true;
+        potion.effects.healing = Some(8);
+        return Raws {
+            monsters: Vec::new(),
+            npcs: Vec::new(),
+            items: vec![potion],
+            props: Vec::new(),
+            factions: Vec::new(),
+            spawn_table: vec![spawn("Health Potion")],
+            town_spawn_table: Vec::new(),
+            tables: HashMap::new(),
+        };
+    }
+
+    #[test]
+    fn shipped_raws_load() {
+        assert!(load_raws(RAWS_PATH).is_ok());
+    }
+
+    #[test]
+    fn misspelled_spawn_table_names_are_rejected() {
+        assert!(RawMaster::new(minimal_raws()).is_ok());
+
+        let mut raws = minimal_raws();
+        raws.spawn_table = vec![spawn("Helth Potion")];
+        let error = RawMaster::new(raws).err().unwrap();
+        assert!(error.contains("Helth Potion"));
+    }
+
+    #[test]
+    fn misspelled_fields_are_rejected() {
+        assert!(serde_json::from_str::<RawEffects>(r#"{ "healing": 8 }"#).is_ok());
+        assert!(serde_json::from_str::<RawEffects>(r#"{ "heeling": 8 }"#).is_err());
+    }
+
+    #[test]
+    fn tables_that_contain_themselves_are_rejected() {
+        let mut raws = minimal_raws();
+        raws.spawn_table = vec![roll_on("potions")];
+        raws.tables
+            .insert("potions".to_string(), vec![roll_on("potions")]);
+        let error = RawMaster::new(raws).err().unwrap();
+        assert!(error.contains("contains itself"));
+    }
+
+    #[test]
+    fn factions_fall_back_to_their_default_reaction() {
+        let raws = load_raws(RAWS_PATH).unwrap();
+        assert_eq!(
+            raws.faction_reaction("Goblinoid", "Player"),
+            Reaction::Attack
+        );
+        assert_eq!(
+            raws.faction_reaction("Vermin", "Goblinoid"),
+            Reaction::Ignore
+        );
+        assert_eq!(raws.faction_reaction("Player", "Vermin"), Reaction::Ignore);
+        assert_eq!(raws.faction_reaction("Nobody", "Player"), Reaction::Ignore);
+    }
+
+    #[test]
+    fn loot_only_drops_as_often_as_its_chance() {
+        let raws = load_raws(RAWS_PATH).unwrap();
+        let mut rng = rltk::RandomNumberGenerator::seeded(3);
+        let always = LootTable {
+            table: "orc_loot".to_string(),
+            chance: 100,
+        };
+        let never = LootTable {
+            table: "orc_loot".to_string(),
+            chance: 0,
+        };
+        for _ in 0..100 {
+            assert!(raws.roll_loot(&always, 1, &mut rng).is_some());
+            assert!(raws.roll_loot(&never, 1, &mut rng).is_none());
+        }
+    }
+
+    #[test]
+    fn loot_from_unknown_tables_is_rejected() {
+        let mut raws = minimal_raws();
+        raws.factions.push(RawFaction {
+            name: "Vermin".to_string(),
+            reactions: HashMap::new(),
+        });
+        raws.monsters.push(RawMonster {
+            name: "Rat".to_string(),
+            renderable: renderable("r"),
+            vision_range: 4,
+            faction: "Vermin".to_string(),
+            level: 1,
+            attributes: RawAttributes::default(),
+            attack: "1d3".to_string(),
+            attack_type: DamageType::Piercing,
+            resistances: None,
+            light_range: None,
+            loot: Some(RawLoot {
+                table: "rat_loot".to_string(),
+                chance: 10,
+            }),
+        });
+        let error = RawMaster::new(raws).err().unwrap();
+        assert!(error.contains("rat_loot"));
+    }
+
+    #[test]
+    fn ranged_weapons_must_fire_ammunition() {
+        let bow = |ammo: &str| {
+            let mut shortbow = item("Shortbow", "}");
+            shortbow.equippable = Some(RawEquippable {
+                slot: EquipmentSlot::Ranged,
+                damage: Some("1d6".to_string()),
+                damage_type: DamageType::Piercing,
+                hit_bonus: None,
+                range: Some(8),
+                ammo: Some(ammo.to_string()),
+                defense_bonus: None,
+                attributes: None,
+                resistances: None,
+            });
+            let mut arrows = item("Arrows", "|");
+            arrows.ammunition = Some(12);
+
+            let mut raws = minimal_raws();
+            raws.items.push(shortbow);
+            raws.items.push(arrows);
+            return RawMaster::new(raws);
+        };
+
+        assert!(bow("Arrows").is_ok());
+        let error = bow("Health Potion").err().unwrap();
+        assert!(error.contains("not ammunition"));
+    }
+}
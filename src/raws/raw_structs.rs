@@ -0,0 +1,208 @@
+use crate::combat::DamageType;
+use crate::gamesystem::AVERAGE_ATTRIBUTE;
+use crate::EquipmentSlot;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Everything in `raws/spawns.json`. Unknown fields are rejected, so a typo
+/// in a field name is caught as soon as the file is loaded.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    pub monsters: Vec<RawMonster>,
+    pub npcs: Vec<RawNpc>,
+    pub items: Vec<RawItem>,
+    pub props: Vec<RawProp>,
+    pub factions: Vec<RawFaction>,
+    pub spawn_table: Vec<RawSpawnEntry>,
+    pub town_spawn_table: Vec<RawSpawnEntry>,
+    /// Named sub-tables that spawn table entries and monster loot can roll
+    /// on, such as "scrolls"
+    #[serde(default)]
+    pub tables: HashMap<String, Vec<RawSpawnEntry>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawRenderable {
+    /// A single character, converted to its code page 437 glyph
+    pub glyph: String,
+    /// An HTML colour such as "#FF0000"
+    pub fg: String,
+    pub order: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawMonster {
+    pub name: String,
+    pub renderable: RawRenderable,
+    pub vision_range: i32,
+    pub faction: String,
+    pub level: i32,
+    #[serde(default)]
+    pub attributes: RawAttributes,
+    /// Damage dice of its natural attack, such as "1d6"
+    pub attack: String,
+    #[serde(default)]
+    pub attack_type: DamageType,
+    pub resistances: Option<RawResistances>,
+    pub light_range: Option<i32>,
+    pub loot: Option<RawLoot>,
+}
+
+/// What a monster might drop when it dies
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawLoot {
+    /// One of the named `tables`
+    pub table: String,
+    /// Percent chance of dropping anything at all
+    pub chance: i32,
+}
+
+/// Any attribute left out is average
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RawAttributes {
+    pub might: i32,
+    pub fitness: i32,
+    pub quickness: i32,
+    pub intelligence: i32,
+}
+
+impl Default for RawAttributes {
+    fn default() -> RawAttributes {
+        return RawAttributes {
+            might: AVERAGE_ATTRIBUTE,
+            fitness: AVERAGE_ATTRIBUTE,
+            quickness: AVERAGE_ATTRIBUTE,
+            intelligence: AVERAGE_ATTRIBUTE,
+        };
+    }
+}
+
+/// How one faction treats members of the others. "Default" covers every
+/// faction that isn't listed.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawFaction {
+    pub name: String,
+    pub reactions: HashMap<String, Reaction>,
+}
+
+#[derive(Deserialize, PartialEq, Copy, Clone, Debug)]
+pub enum Reaction {
+    Ignore,
+    Attack,
+}
+
+/// Townsfolk: they stand in the way, but can't fight or be fought
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawNpc {
+    pub name: String,
+    pub renderable: RawRenderable,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawItem {
+    pub name: String,
+    pub renderable: RawRenderable,
+    #[serde(default)]
+    pub consumable: bool,
+    /// Items with a range are aimed at a tile when used
+    pub range: Option<i32>,
+    #[serde(default)]
+    pub effects: RawEffects,
+    pub equippable: Option<RawEquippable>,
+    /// Makes the item stackable ammunition, found in stacks of this size
+    pub ammunition: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawEquippable {
+    pub slot: EquipmentSlot,
+    /// Damage dice, such as "1d8", for a weapon
+    pub damage: Option<String>,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub hit_bonus: Option<i32>,
+    /// How far a ranged weapon shoots
+    pub range: Option<i32>,
+    /// The ammunition item a ranged weapon uses up
+    pub ammo: Option<String>,
+    pub defense_bonus: Option<i32>,
+    /// Added to the wearer's attributes, e.g. `{ "quickness": -1 }`
+    pub attributes: Option<RawAttributeBonus>,
+    /// Protects whoever has it equipped
+    pub resistances: Option<RawResistances>,
+}
+
+/// Any attribute left out is unchanged
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct RawAttributeBonus {
+    pub might: i32,
+    pub fitness: i32,
+    pub quickness: i32,
+    pub intelligence: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawResistances {
+    #[serde(default)]
+    pub resistant: Vec<DamageType>,
+    #[serde(default)]
+    pub weak: Vec<DamageType>,
+    #[serde(default)]
+    pub immune: Vec<DamageType>,
+}
+
+/// What happens to whoever uses an item or sets off a trap
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RawEffects {
+    pub healing: Option<i32>,
+    pub damage: Option<i32>,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub area_of_effect: Option<i32>,
+    pub confusion: Option<i32>,
+    #[serde(default)]
+    pub teleport: bool,
+}
+
+/// Anything that isn't a creature or an item: traps, torches and the like
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawProp {
+    pub name: String,
+    pub renderable: RawRenderable,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub entry_trigger: bool,
+    #[serde(default)]
+    pub single_activation: bool,
+    #[serde(default)]
+    pub effects: RawEffects,
+    pub light_range: Option<i32>,
+}
+
+/// Either `name` something to spawn or `table` another table to roll on.
+/// The weight at a depth is `weight + per_depth * depth`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawSpawnEntry {
+    pub name: Option<String>,
+    pub table: Option<String>,
+    pub weight: i32,
+    #[serde(default)]
+    pub per_depth: f32,
+    pub min_depth: Option<i32>,
+    pub max_depth: Option<i32>,
+}
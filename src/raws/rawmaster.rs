@@ -0,0 +1,433 @@
+use super::raw_structs::{
+    RawEffects, RawEquippable, RawRenderable, RawResistances, RawSpawnEntry, Raws, Reaction,
+};
+use crate::combat::Dice;
+use crate::gamesystem::npc_hp;
+use crate::random_table::{RandomEntry, RandomTable};
+use crate::{
+    Ammunition, AreaOfEffect, AttributeBonus, Attributes, BlocksTile, Confusion, Consumable,
+    DefenseBonus, EntryTrigger, EquipmentSlot, Equippable, Faction, Hidden, InflictsDamage, Item,
+    LightSource, LootTable, MeleeWeapon, Monster, Name, NaturalAttack, Pool, Pools, Position,
+    ProvidesHealing, Ranged, RangedWeapon, Renderable, Resistances, SerializeMe, SingleActivation,
+    TeleportsTarget, Viewshed,
+};
+use rltk::{RandomNumberGenerator, RGB};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+use std::collections::HashMap;
+
+/// Which list a named entity was defined in, and where in that list
+#[derive(Clone, Copy)]
+enum RawKind {
+    Monster(usize),
+    Npc(usize),
+    Item(usize),
+    Prop(usize),
+}
+
+/// The loaded raws, indexed by name so entities can be spawned from them
+pub struct RawMaster {
+    raws: Raws,
+    index: HashMap<String, RawKind>,
+    spawn_table: RandomTable<String>,
+    town_spawn_table: RandomTable<String>,
+    /// The named `tables`, for rolling loot
+    tables: HashMap<String, RandomTable<String>>,
+    /// Faction name to how it reacts to each other faction
+    reactions: HashMap<String, HashMap<String, Reaction>>,
+}
+
+impl RawMaster {
+    /// Indexes the raws, checking that every name is unique, every glyph and
+    /// colour can be drawn and every spawn table entry names something
+    pub fn new(raws: Raws) -> Result<RawMaster, String> {
+        let mut index = HashMap::new();
+        let mut entries: Vec<(&String, &RawRenderable, RawKind)> = Vec::new();
+        for (i, monster) in raws.monsters.iter().enumerate() {
+            entries.push((&monster.name, &monster.renderable, RawKind::Monster(i)));
+        }
+        for (i, npc) in raws.npcs.iter().enumerate() {
+            entries.push((&npc.name, &npc.renderable, RawKind::Npc(i)));
+        }
+        for (i, item) in raws.items.iter().enumerate() {
+            entries.push((&item.name, &item.renderable, RawKind::Item(i)));
+        }
+        for (i, prop) in raws.props.iter().enumerate() {
+            entries.push((&prop.name, &prop.renderable, RawKind::Prop(i)));
+        }
+
+        for (name, renderable, kind) in entries {
+            if index.insert(name.clone(), kind).is_some() {
+                return Err(format!("'{}' is defined more than once", name));
+            }
+            if renderable.glyph.chars().count() != 1 {
+                return Err(format!("'{}' needs a single character glyph", name));
+            }
+            if RGB::from_hex(&renderable.fg).is_err() {
+                return Err(format!(
+                    "'{}' has an invalid colour {}",
+                    name, renderable.fg
+                ));
+            }
+        }
+
+        let mut reactions = HashMap::new();
+        for faction in raws.factions.iter() {
+            reactions.insert(faction.name.clone(), faction.reactions.clone());
+        }
+        for faction in raws.factions.iter() {
+            for other in faction.reactions.keys() {
+                if other != "Default" && !reactions.contains_key(other) {
+                    return Err(format!(
+                        "faction '{}' reacts to unknown faction '{}'",
+                        faction.name, other
+                    ));
+                }
+            }
+        }
+        for item in raws.items.iter() {
+            if item.ammunition.is_some_and(|count| count < 1) {
+                return Err(format!("'{}' needs at least one in a stack", item.name));
+            }
+            if let Some(equippable) = &item.equippable {
+                check_equippable(&raws, &index, &item.name, equippable)?;
+            }
+        }
+
+        let mut tables = HashMap::new();
+        for (name, entries) in raws.tables.iter() {
+            let table = build_table(&raws, &index, entries, &mut vec![name.clone()])?;
+            tables.insert(name.clone(), table);
+        }
+
+        for monster in raws.monsters.iter() {
+            Dice::parse(&monster.attack)
+                .map_err(|error| format!("'{}': {}", monster.name, error))?;
+            if !reactions.contains_key(&monster.faction) {
+                return Err(format!(
+                    "'{}' belongs to unknown faction '{}'",
+                    monster.name, monster.faction
+                ));
+            }
+            if let Some(loot) = &monster.loot {
+                if !tables.contains_key(&loot.table) {
+                    return Err(format!(
+                        "'{}' drops loot from unknown table '{}'",
+                        monster.name, loot.table
+                    ));
+                }
+                if loot.chance < 0 || loot.chance > 100 {
+                    return Err(format!(
+                        "'{}' needs a loot chance between 0 and 100",
+                        monster.name
+                    ));
+                }
+            }
+        }
+
+        let spawn_table = build_table(&raws, &index, &raws.spawn_table, &mut Vec::new())?;
+        let town_spawn_table = build_table(&raws, &index, &raws.town_spawn_table, &mut Vec::new())?;
+
+        return Ok(RawMaster {
+            raws,
+            index,
+            spawn_table,
+            town_spawn_table,
+            tables,
+            reactions,
+        });
+    }
+
+    /// How members of `faction` treat members of `other`
+    pub fn faction_reaction(&self, faction: &str, other: &str) -> Reaction {
+        if let Some(reactions) = self.reactions.get(faction) {
+            if let Some(reaction) = reactions.get(other).or_else(|| reactions.get("Default")) {
+                return *reaction;
+            }
+        }
+        return Reaction::Ignore;
+    }
+
+    /// What may be found on a dungeon level
+    pub fn spawn_table(&self) -> &RandomTable<String> {
+        return &self.spawn_table;
+    }
+
+    /// Who might be found in a house in town
+    pub fn town_spawn_table(&self) -> &RandomTable<String> {
+        return &self.town_spawn_table;
+    }
+
+    /// Rolls a monster's loot: the name of the item it drops, if any
+    pub fn roll_loot(
+        &self,
+        loot: &LootTable,
+        depth: i32,
+        rng: &mut RandomNumberGenerator,
+    ) -> Option<String> {
+        if rng.roll_dice(1, 100) > loot.chance {
+            return None;
+        }
+        return self.tables.get(&loot.table)?.roll(depth, rng);
+    }
+
+    /// Creates the named entity at the given position.
+    /// Returns None if nothing in the raws has that name.
+    pub fn spawn_named_entity(
+        &self,
+        ecs: &mut World,
+        name: &str,
+        x: i32,
+        y: i32,
+    ) -> Option<Entity> {
+        let kind = *self.index.get(name)?;
+        let builder = ecs.create_entity().with(Position { x, y }).with(Name {
+            value: name.to_string(),
+        });
+
+        let builder = match kind {
+            RawKind::Monster(i) => {
+                let monster = &self.raws.monsters[i];
+                let attributes = &monster.attributes;
+                let mut builder = builder
+                    .with(renderable(&monster.renderable))
+                    .with(Monster {})
+                    .with(Faction {
+                        name: monster.faction.clone(),
+                    })
+                    .with(BlocksTile {})
+                    .with(Viewshed {
+                        visible_tiles: Vec::new(),
+                        range: monster.vision_range,
+                        dirty: true,
+                    })
+                    .with(Attributes::new(
+                        attributes.might,
+                        attributes.fitness,
+                        attributes.quickness,
+                        attributes.intelligence,
+                    ))
+                    .with(NaturalAttack {
+                        damage: Dice::parse(&monster.attack).unwrap(),
+                        damage_type: monster.attack_type,
+                    })
+                    .with(Pools {
+                        hit_points: Pool::new(npc_hp(attributes.fitness, monster.level)),
+                        level: monster.level,
+                    });
+                if let Some(range) = monster.light_range {
+                    builder = builder.with(LightSource { range });
+                }
+                if let Some(raw) = &monster.resistances {
+                    builder = builder.with(resistances(raw));
+                }
+                if let Some(loot) = &monster.loot {
+                    builder = builder.with(LootTable {
+                        table: loot.table.clone(),
+                        chance: loot.chance,
+                    });
+                }
+                builder
+            }
+            RawKind::Npc(i) => {
+                let npc = &self.raws.npcs[i];
+                builder
+                    .with(renderable(&npc.renderable))
+                    .with(BlocksTile {})
+            }
+            RawKind::Item(i) => {
+                let item = &self.raws.items[i];
+                let mut builder = builder.with(renderable(&item.renderable)).with(Item {});
+                if item.consumable {
+                    builder = builder.with(Consumable {});
+                }
+                if let Some(count) = item.ammunition {
+                    builder = builder.with(Ammunition { count });
+                }
+                if let Some(range) = item.range {
+                    builder = builder.with(Ranged { range });
+                }
+                if let Some(equippable) = &item.equippable {
+                    builder = builder.with(Equippable {
+                        slot: equippable.slot,
+                    });
+                    let hit_bonus = equippable.hit_bonus.unwrap_or(0);
+                    if let Some(ammo) = &equippable.ammo {
+                        builder = builder.with(RangedWeapon {
+                            range: equippable.range.unwrap(),
+                            damage: Dice::parse(equippable.damage.as_ref().unwrap()).unwrap(),
+                            damage_type: equippable.damage_type,
+                            hit_bonus,
+                            ammo: ammo.clone(),
+                        });
+                    } else if let Some(damage) = &equippable.damage {
+                        builder = builder.with(MeleeWeapon {
+                            damage: Dice::parse(damage).unwrap(),
+                            damage_type: equippable.damage_type,
+                            hit_bonus,
+                        });
+                    }
+                    if let Some(defense) = equippable.defense_bonus {
+                        builder = builder.with(DefenseBonus { defense });
+                    }
+                    if let Some(raw) = &equippable.attributes {
+                        builder = builder.with(AttributeBonus {
+                            might: raw.might,
+                            fitness: raw.fitness,
+                            quickness: raw.quickness,
+                            intelligence: raw.intelligence,
+                        });
+                    }
+                    if let Some(raw) = &equippable.resistances {
+                        builder = builder.with(resistances(raw));
+                    }
+                }
+                with_effects(builder, &item.effects)
+            }
+            RawKind::Prop(i) => {
+                let prop = &self.raws.props[i];
+                let mut builder = builder.with(renderable(&prop.renderable));
+                if prop.hidden {
+                    builder = builder.with(Hidden {});
+                }
+                if prop.entry_trigger {
+                    builder = builder.with(EntryTrigger {});
+                }
+                if prop.single_activation {
+                    builder = builder.with(SingleActivation {});
+                }
+                if let Some(range) = prop.light_range {
+                    builder = builder.with(LightSource { range });
+                }
+                with_effects(builder, &prop.effects)
+            }
+        };
+
+        return Some(builder.marked::<SimpleMarker<SerializeMe>>().build());
+    }
+}
+
+/// Checks a weapon's dice, and that ranged weapons (and only they) have a
+/// range and ammunition to fire
+fn check_equippable(
+    raws: &Raws,
+    index: &HashMap<String, RawKind>,
+    name: &str,
+    equippable: &RawEquippable,
+) -> Result<(), String> {
+    if let Some(damage) = &equippable.damage {
+        Dice::parse(damage).map_err(|error| format!("'{}': {}", name, error))?;
+    }
+
+    if equippable.slot != EquipmentSlot::Ranged {
+        if equippable.range.is_some() || equippable.ammo.is_some() {
+            return Err(format!(
+                "'{}' has a range or ammo but isn't a ranged weapon",
+                name
+            ));
+        }
+        return Ok(());
+    }
+
+    if equippable.damage.is_none() || equippable.range.is_none() {
+        return Err(format!("ranged weapon '{}' needs damage and a range", name));
+    }
+    let ammo = equippable
+        .ammo
+        .as_ref()
+        .ok_or_else(|| format!("ranged weapon '{}' needs ammo", name))?;
+    let is_ammunition = match index.get(ammo) {
+        Some(RawKind::Item(i)) => raws.items[*i].ammunition.is_some(),
+        _ => false,
+    };
+    if !is_ammunition {
+        return Err(format!(
+            "'{}' fires '{}', which is not ammunition",
+            name, ammo
+        ));
+    }
+    return Ok(());
+}
+
+/// Turns raw spawn entries into a table, following sub-tables. `parents`
+/// holds the sub-tables being built, to catch a table that contains itself.
+fn build_table(
+    raws: &Raws,
+    index: &HashMap<String, RawKind>,
+    entries: &[RawSpawnEntry],
+    parents: &mut Vec<String>,
+) -> Result<RandomTable<String>, String> {
+    let mut table = RandomTable::new();
+    for raw in entries.iter() {
+        let entry = match (&raw.name, &raw.table) {
+            (Some(name), None) => {
+                if !index.contains_key(name) {
+                    return Err(format!(
+                        "spawn table entry '{}' is not a known monster, npc, item or prop",
+                        name
+                    ));
+                }
+                RandomEntry::new(name.clone(), raw.weight)
+            }
+            (None, Some(table_name)) => {
+                let sub_entries = raws
+                    .tables
+                    .get(table_name)
+                    .ok_or_else(|| format!("there is no spawn table called '{}'", table_name))?;
+                if parents.contains(table_name) {
+                    return Err(format!("spawn table '{}' contains itself", table_name));
+                }
+                parents.push(table_name.clone());
+                let sub_table = build_table(raws, index, sub_entries, parents)?;
+                parents.pop();
+                RandomEntry::table(sub_table, raw.weight)
+            }
+            _ => return Err("every spawn table entry needs either a name or a table".to_string()),
+        };
+
+        table = table.add_entry(entry.per_depth(raw.per_depth).depths(
+            raw.min_depth.unwrap_or(i32::MIN),
+            raw.max_depth.unwrap_or(i32::MAX),
+        ));
+    }
+    return Ok(table);
+}
+
+fn renderable(raw: &RawRenderable) -> Renderable {
+    return Renderable {
+        glyph: rltk::to_cp437(raw.glyph.chars().next().unwrap()),
+        fg: RGB::from_hex(&raw.fg).unwrap(),
+        bg: RGB::named(rltk::BLACK),
+        render_order: raw.order,
+    };
+}
+
+fn resistances(raw: &RawResistances) -> Resistances {
+    return Resistances {
+        resistant: raw.resistant.clone(),
+        weak: raw.weak.clone(),
+        immune: raw.immune.clone(),
+    };
+}
+
+fn with_effects<'a>(mut builder: EntityBuilder<'a>, effects: &RawEffects) -> EntityBuilder<'a> {
+    if let Some(heal_amount) = effects.healing {
+        builder = builder.with(ProvidesHealing { heal_amount });
+    }
+    if let Some(damage) = effects.damage {
+        builder = builder.with(InflictsDamage {
+            damage,
+            damage_type: effects.damage_type,
+        });
+    }
+    if let Some(radius) = effects.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(turns) = effects.confusion {
+        builder = builder.with(Confusion { turns });
+    }
+    if effects.teleport {
+        builder = builder.with(TeleportsTarget {});
+    }
+    return builder;
+}
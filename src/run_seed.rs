@@ -0,0 +1,26 @@
+use rltk::RandomNumberGenerator;
+
+/// The seed a run was started with.
+/// Levels are generated from the seed and their depth alone, so "seed X,
+/// depth 3" is enough to reproduce a level.
+pub struct RunSeed {
+    pub value: u64,
+}
+
+impl RunSeed {
+    /// Picks a seed for a new run, small enough to be read off the screen and
+    /// typed back in
+    pub fn random() -> RunSeed {
+        let mut rng = RandomNumberGenerator::new();
+        return RunSeed {
+            value: rng.range(0, u32::MAX as u64),
+        };
+    }
+
+    /// The generator used to build the level at the given depth
+    pub fn level_rng(&self, depth: i32) -> RandomNumberGenerator {
+        return RandomNumberGenerator::seeded(
+            self.value ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        );
+    }
+}
@@ -39,9 +39,15 @@ macro_rules! deserialize_individually {
 #[cfg(not(target_arch = "wasm32"))]
 pub fn save_game(ecs: &mut World) {
     let map_copy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let seed = ecs.fetch::<super::RunSeed>().value;
+    let dungeon_master = super::DungeonMaster::clone(&ecs.fetch::<super::DungeonMaster>());
     let save_helper = ecs
         .create_entity()
-        .with(SerializationHelper { map: map_copy })
+        .with(SerializationHelper {
+            map: map_copy,
+            seed,
+            dungeon_master,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -59,13 +65,17 @@ pub fn save_game(ecs: &mut World) {
             serializer,
             data,
             Position,
+            OtherLevelPosition,
             Renderable,
             Player,
             Viewshed,
             Monster,
+            Faction,
+            Wading,
             Name,
             BlocksTile,
-            CombatStats,
+            Attributes,
+            Pools,
             SufferDamage,
             WantsToMelee,
             Item,
@@ -74,6 +84,13 @@ pub fn save_game(ecs: &mut World) {
             InflictsDamage,
             AreaOfEffect,
             Confusion,
+            Hidden,
+            EntryTrigger,
+            EntityMoved,
+            SingleActivation,
+            TeleportsTarget,
+            LightSource,
+            LootTable,
             ProvidesHealing,
             InBackpack,
             WantsToPickUpItem,
@@ -81,6 +98,14 @@ pub fn save_game(ecs: &mut World) {
             WantsToDropItem,
             SerializationHelper,
             Equippable,
+            MeleeWeapon,
+            NaturalAttack,
+            Resistances,
+            RangedWeapon,
+            Ammunition,
+            WantsToShoot,
+            DefenseBonus,
+            AttributeBonus,
             Equipped,
             WantsToRemoveEquipment
         );
@@ -117,13 +142,17 @@ pub fn load_game(ecs: &mut World) {
             deserializer,
             data,
             Position,
+            OtherLevelPosition,
             Renderable,
             Player,
             Viewshed,
             Monster,
+            Faction,
+            Wading,
             Name,
             BlocksTile,
-            CombatStats,
+            Attributes,
+            Pools,
             SufferDamage,
             WantsToMelee,
             Item,
@@ -132,6 +161,13 @@ pub fn load_game(ecs: &mut World) {
             InflictsDamage,
             AreaOfEffect,
             Confusion,
+            Hidden,
+            EntryTrigger,
+            EntityMoved,
+            SingleActivation,
+            TeleportsTarget,
+            LightSource,
+            LootTable,
             ProvidesHealing,
             InBackpack,
             WantsToPickUpItem,
@@ -139,6 +175,14 @@ pub fn load_game(ecs: &mut World) {
             WantsToDropItem,
             SerializationHelper,
             Equippable,
+            MeleeWeapon,
+            NaturalAttack,
+            Resistances,
+            RangedWeapon,
+            Ammunition,
+            WantsToShoot,
+            DefenseBonus,
+            AttributeBonus,
             Equipped,
             WantsToRemoveEquipment
         );
@@ -154,7 +198,17 @@ pub fn load_game(ecs: &mut World) {
         for (entity, helper) in (&entities, &serialization_helper).join() {
             let mut world_map = ecs.write_resource::<super::map::Map>();
             *world_map = helper.map.clone();
-            world_map.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            world_map.tile_content = vec![Vec::new(); world_map.tiles.len()];
+
+            // the dice can't be restored mid-sequence, so reseed them from the
+            // run seed and the current depth to keep loaded games repeatable
+            let mut seed = ecs.write_resource::<super::RunSeed>();
+            seed.value = helper.seed;
+            let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+            *rng = seed.level_rng(world_map.depth);
+
+            let mut dungeon_master = ecs.write_resource::<super::DungeonMaster>();
+            *dungeon_master = helper.dungeon_master.clone();
             entity_to_delete = Some(entity);
         }
 
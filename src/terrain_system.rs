@@ -0,0 +1,39 @@
+use super::{combat::DamageType, Damage, Map, Pools, Position, RunState, SufferDamage, TileType};
+use specs::prelude::*;
+
+const LAVA_DAMAGE: i32 = 5;
+
+/// Burns everything standing in lava, once at the end of every round
+pub struct TerrainSystem {}
+
+impl<'a> System<'a> for TerrainSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Pools>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, runstate, entities, positions, pools, mut suffer_damage) = data;
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, pos, _stats) in (&entities, &positions, &pools).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if map.tiles[idx] != TileType::Lava {
+                continue;
+            }
+
+            SufferDamage::new_damage(
+                &mut suffer_damage,
+                entity,
+                Damage::new(LAVA_DAMAGE, DamageType::Fire, "Lava"),
+            );
+        }
+    }
+}
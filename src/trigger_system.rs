@@ -0,0 +1,129 @@
+use super::{
+    Confusion, Damage, EntityMoved, EntryTrigger, GameLog, Hidden, InflictsDamage, Map, Name,
+    Position, SingleActivation, SufferDamage, TeleportsTarget, TileType, Viewshed,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Gives up looking for a free tile to teleport to after this many tries
+const TELEPORT_ATTEMPTS: i32 = 100;
+
+/// Sets off traps under everything that moved this turn
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Entities<'a>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, InflictsDamage>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, TeleportsTarget>,
+        ReadStorage<'a, SingleActivation>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            player_entity,
+            mut player_pos,
+            mut log,
+            mut rng,
+            entities,
+            mut entity_moved,
+            mut positions,
+            entry_triggers,
+            mut hidden,
+            names,
+            inflicts_damage,
+            mut suffer_damage,
+            mut confusion,
+            teleports,
+            single_activation,
+            mut viewsheds,
+        ) = data;
+
+        let moved: Vec<(Entity, usize)> = (&entities, &entity_moved, &positions)
+            .join()
+            .map(|(entity, _moved, pos)| (entity, map.xy_idx(pos.x, pos.y)))
+            .collect();
+        entity_moved.clear();
+
+        for (victim, idx) in moved {
+            for trap in map.tile_content[idx].iter() {
+                if *trap == victim || entry_triggers.get(*trap).is_none() {
+                    continue;
+                }
+
+                // a trap that goes off in plain sight is no longer a secret
+                let is_player = victim == *player_entity;
+                if is_player || map.visible_tiles[idx] {
+                    hidden.remove(*trap);
+                    let trap_name = &names.get(*trap).unwrap().value;
+                    if is_player {
+                        log.entries.push(format!("You step on a {}!", trap_name));
+                    } else if let Some(name) = names.get(victim) {
+                        log.entries
+                            .push(format!("{} steps on a {}!", name.value, trap_name));
+                    }
+                }
+
+                if let Some(damage) = inflicts_damage.get(*trap) {
+                    let trap_name = &names.get(*trap).unwrap().value;
+                    SufferDamage::new_damage(
+                        &mut suffer_damage,
+                        victim,
+                        Damage::new(damage.damage, damage.damage_type, trap_name),
+                    );
+                }
+
+                let turns = confusion.get(*trap).map(|confused| confused.turns);
+                if let Some(turns) = turns {
+                    confusion
+                        .insert(victim, Confusion { turns })
+                        .expect("Unable to insert status");
+                }
+
+                if teleports.get(*trap).is_some() {
+                    if let Some(destination) = random_free_tile(&map, &mut rng) {
+                        let pos = positions.get_mut(victim).unwrap();
+                        pos.x = destination.x;
+                        pos.y = destination.y;
+                        if is_player {
+                            *player_pos = destination;
+                        }
+                        if let Some(viewshed) = viewsheds.get_mut(victim) {
+                            viewshed.dirty = true;
+                        }
+                    }
+                }
+
+                if single_activation.get(*trap).is_some() {
+                    entities.delete(*trap).expect("Unable to delete trap");
+                }
+            }
+        }
+    }
+}
+
+fn random_free_tile(map: &Map, rng: &mut RandomNumberGenerator) -> Option<Point> {
+    for _i in 0..TELEPORT_ATTEMPTS {
+        let x = rng.roll_dice(1, map.width - 2);
+        let y = rng.roll_dice(1, map.height - 2);
+        let idx = map.xy_idx(x, y);
+        if map.tiles[idx] == TileType::Floor && !map.blocked_tiles[idx] {
+            return Some(Point::new(x, y));
+        }
+    }
+    return None;
+}
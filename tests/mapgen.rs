@@ -0,0 +1,35 @@
+//! Runs the mapgen binary the way scripts do, so anything else that ends up
+//! on stdout shows as broken JSON.
+use hellorust::map_builders::BUILDER_NAMES;
+use std::process::Command;
+
+const SEEDS: u64 = 8;
+
+#[test]
+fn json_output_parses_for_every_builder() {
+    for builder in BUILDER_NAMES.iter() {
+        for seed in 0..SEEDS {
+            let seed = seed.to_string();
+            let output = Command::new(env!("CARGO_BIN_EXE_mapgen"))
+                .args(["--seed", &seed, "--depth", "2"])
+                .args(["--builder", builder, "--format", "json"])
+                .output()
+                .expect("Unable to run mapgen");
+            let context = format!("{} --seed {}", builder, seed);
+            assert!(output.status.success(), "{}: mapgen failed", context);
+
+            let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+            let level: serde_json::Value = match serde_json::from_str(&stdout) {
+                Ok(level) => level,
+                Err(e) => panic!("{}: stdout is not JSON ({}):\n{}", context, e, stdout),
+            };
+            assert_eq!(level["builder"], *builder, "{}", context);
+            assert_eq!(
+                level["tiles"].as_array().map(|rows| rows.len() as i64),
+                level["height"].as_i64(),
+                "{}",
+                context
+            );
+        }
+    }
+}